@@ -0,0 +1,963 @@
+use crate::definition::{DefinitionType, Scope};
+use crate::server::{language, LSPServer, LanguageStandard};
+use crate::sources::LSPSupport;
+use log::debug;
+use regex::Regex;
+use ropey::Rope;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::*;
+
+/// run by the add source directory quick fix, so the directory is indexed without a restart
+pub const ADD_SOURCE_DIR: &str = "veridian.addSourceDir";
+
+impl LSPServer {
+    pub fn code_action(&self, params: CodeActionParams) -> Option<CodeActionResponse> {
+        let uri = params.text_document.uri;
+        debug!("code_action: {}", &uri);
+        // finding the references waits for the parse, so it is done before the file is locked
+        let mut actions: Vec<CodeActionOrCommand> = params
+            .context
+            .diagnostics
+            .iter()
+            .filter_map(|x| self.rename_convention(&uri, x))
+            .map(CodeActionOrCommand::CodeAction)
+            .collect();
+        let file_id = *self.srcs.names.read().unwrap().get(&uri)?;
+        let file = self.srcs.get_file(file_id)?;
+        let file = file.read().ok()?;
+        let extensions = self.srcs.extensions.read().ok()?;
+        for diagnostic in &params.context.diagnostics {
+            actions.extend(
+                quick_fixes(&uri, &file.text, diagnostic, &extensions)
+                    .into_iter()
+                    .map(CodeActionOrCommand::CodeAction),
+            );
+            actions.extend(
+                self.add_source_dir(&file.text, diagnostic)
+                    .map(CodeActionOrCommand::CodeAction),
+            );
+        }
+        if actions.is_empty() {
+            None
+        } else {
+            Some(actions)
+        }
+    }
+
+    /// add the directory of the indexed file declaring an unknown name to source_dirs, so it is
+    /// still found after a restart, ex. when the file was only opened in the editor
+    fn add_source_dir(&self, rope: &Rope, diagnostic: &Diagnostic) -> Option<CodeAction> {
+        let Some(NumberOrString::String(code)) = &diagnostic.code else {
+            return None;
+        };
+        if !matches!(
+            code.as_str(),
+            "unresolved-module" | "unresolved-package" | "unresolved-interface"
+        ) {
+            return None;
+        }
+        let name = rope
+            .slice(rope.range_to_char_range(&diagnostic.range))
+            .to_string();
+        let dir = self.declaring_file(&name)?.parent()?.to_path_buf();
+        if self.srcs.source_dirs.read().ok()?.contains(&dir) {
+            return None;
+        }
+        let root = self.root.read().ok()?.clone()?;
+        source_dir_fix(&root, &dir, diagnostic)
+    }
+
+    /// the indexed file declaring a module, interface, program, primitive or package, from the
+    /// scope tree or the text of files that couldn't be parsed
+    fn declaring_file(&self, name: &str) -> Option<PathBuf> {
+        let mut indexed: HashSet<Url> = HashSet::new();
+        if let Some(scope_tree) = self.srcs.scope_tree.read().ok()?.as_ref() {
+            let declared = scope_tree.scopes.iter().find(|x| {
+                x.ident() == name
+                    && matches!(
+                        x.symbol_kind(),
+                        SymbolKind::MODULE | SymbolKind::INTERFACE | SymbolKind::PACKAGE
+                    )
+            });
+            if let Some(scope) = declared {
+                return scope.url().to_file_path().ok();
+            }
+            indexed.extend(scope_tree.scopes.iter().map(|x| x.url()));
+        }
+        let re = Regex::new(&format!(
+            r"(?m)^\s*(?:extern\s+)?(?:module|macromodule|interface|program|primitive|package)\s+(?:(?:static|automatic)\s+)?{}\b",
+            regex::escape(name)
+        ))
+        .ok()?;
+        let files = self.srcs.files.read().ok()?.clone();
+        // the file of the code action is already locked, a file being edited is skipped
+        files.iter().find_map(|x| {
+            let file = x.try_read().ok()?;
+            if indexed.contains(&file.uri) || !re.is_match(&file.text.to_string()) {
+                return None;
+            }
+            file.uri.to_file_path().ok()
+        })
+    }
+
+    /// rename a declaration breaking a naming convention, and its references, to the name
+    /// suggested with the diagnostic
+    fn rename_convention(&self, uri: &Url, diagnostic: &Diagnostic) -> Option<CodeAction> {
+        if diagnostic.code != Some(NumberOrString::String("naming-convention".to_owned())) {
+            return None;
+        }
+        let name = diagnostic.data.as_ref()?.as_str()?;
+        let locations = if self.is_file_local(uri, diagnostic.range.start)? {
+            self.references(ReferenceParams {
+                text_document_position: TextDocumentPositionParams::new(
+                    TextDocumentIdentifier::new(uri.clone()),
+                    diagnostic.range.start,
+                ),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: ReferenceContext {
+                    include_declaration: true,
+                },
+            })?
+        } else {
+            // ports, modules, parameters and subroutines are referenced from other files
+            self.workspace_references(uri, diagnostic.range.start)?
+        };
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for location in locations {
+            changes
+                .entry(location.uri)
+                .or_default()
+                .push(TextEdit::new(location.range, name.to_owned()));
+        }
+        Some(CodeAction {
+            title: format!("Rename to '{}'", name),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit {
+                changes: Some(changes),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    /// whether the declaration at a position can't be referenced outside its file, a net or
+    /// variable declared in a module, interface or program that isn't a port
+    fn is_file_local(&self, uri: &Url, pos: Position) -> Option<bool> {
+        let file_id = *self.srcs.names.read().ok()?.get(uri)?;
+        self.srcs.wait_parse_ready(file_id, false);
+        let byte_idx = self
+            .srcs
+            .get_file(file_id)?
+            .read()
+            .ok()?
+            .text
+            .pos_to_byte(&pos);
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let unit = scope_tree.as_ref()?.scopes.iter().find(|x| {
+            &x.url() == uri
+                && x.start() <= byte_idx
+                && byte_idx <= x.end()
+                && matches!(x.symbol_kind(), SymbolKind::MODULE | SymbolKind::INTERFACE)
+        })?;
+        Some(declares_local(unit.as_ref(), byte_idx))
+    }
+
+    /// references from the whole workspace, None if they can only be found in the document
+    #[allow(unused_variables)]
+    fn workspace_references(&self, uri: &Url, pos: Position) -> Option<Vec<Location>> {
+        #[cfg(feature = "veridian_slang")]
+        return self.slang_references(uri, pos, true);
+        #[cfg(not(feature = "veridian_slang"))]
+        None
+    }
+
+    /// index the directories added by a quick fix, and lint the open documents again
+    pub fn execute_command(&self, params: ExecuteCommandParams) -> Vec<PublishDiagnosticsParams> {
+        debug!("execute_command: {}", &params.command);
+        if params.command != ADD_SOURCE_DIR {
+            return Vec::new();
+        }
+        for dir in params.arguments.iter().filter_map(Value::as_str) {
+            self.srcs.add_dir(PathBuf::from(dir));
+        }
+        let mut diagnostics: Vec<PublishDiagnosticsParams> = Vec::new();
+        for (uri, _) in self.srcs.open_buffers() {
+            diagnostics.append(&mut self.did_save(DidSaveTextDocumentParams {
+                text_document: TextDocumentIdentifier::new(uri),
+                text: None,
+            }));
+        }
+        diagnostics
+    }
+}
+
+/// whether a scope or the scopes nested in it declare a net or variable, that isn't a port, at
+/// a byte
+fn declares_local(scope: &dyn Scope, byte_idx: usize) -> bool {
+    scope.defs().iter().any(|x| {
+        x.byte_idx() == byte_idx
+            && x.symbol_kind() == SymbolKind::VARIABLE
+            && !matches!(x.def_type(), DefinitionType::Port)
+    }) || scope
+        .scopes()
+        .iter()
+        .any(|x| declares_local(x.as_ref(), byte_idx))
+}
+
+/// quick fixes for a diagnostic, keyed off the rule code reported by the linter
+fn quick_fixes(
+    uri: &Url,
+    rope: &Rope,
+    diagnostic: &Diagnostic,
+    extensions: &HashMap<String, LanguageStandard>,
+) -> Vec<CodeAction> {
+    let code = match &diagnostic.code {
+        Some(NumberOrString::String(code)) => code.as_str(),
+        _ => return Vec::new(),
+    };
+    let verilator = diagnostic.source.as_deref() == Some("verilator");
+    let mut fixes: Vec<CodeAction> = Vec::new();
+    match code {
+        "CASEINCOMPLETE" | "missing-case-default" => {
+            fixes.extend(case_default(uri, rope, diagnostic))
+        }
+        "IMPLICIT" => {
+            fixes.extend(declare_net(uri, rope, diagnostic));
+            fixes.extend(default_nettype(uri, rope, diagnostic));
+        }
+        "WIDTH" | "WIDTHEXPAND" | "WIDTHTRUNC" => {
+            fixes.extend(width_cast(uri, rope, diagnostic, extensions))
+        }
+        "DECLFILENAME" => fixes.append(&mut rename_declaration(uri, rope, diagnostic)),
+        _ => (),
+    }
+    if verilator {
+        fixes.push(lint_off(uri, rope, diagnostic, code));
+    }
+    fixes.push(disable_next_line(uri, rope, diagnostic, code));
+    fixes
+}
+
+fn quick_fix(
+    title: String,
+    diagnostic: &Diagnostic,
+    uri: &Url,
+    edits: Vec<TextEdit>,
+) -> CodeAction {
+    CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// the text of a line without the line ending
+fn line_text(rope: &Rope, line: u32) -> String {
+    match rope.get_line(line as usize) {
+        Some(text) => text.to_string().trim_end_matches(['\r', '\n']).to_string(),
+        None => String::new(),
+    }
+}
+
+fn indentation(text: &str) -> String {
+    text.chars().take_while(|x| x.is_whitespace()).collect()
+}
+
+/// insert whole lines before a line, using its indentation
+fn insert_before_line(rope: &Rope, line: u32, text: &str) -> TextEdit {
+    let indent = indentation(&line_text(rope, line));
+    TextEdit::new(
+        Range::new(Position::new(line, 0), Position::new(line, 0)),
+        format!("{}{}\n", indent, text),
+    )
+}
+
+/// insert whole lines after a line, using its indentation
+fn insert_after_line(rope: &Rope, line: u32, text: &str) -> TextEdit {
+    let current = line_text(rope, line);
+    let indent = indentation(&current);
+    let end = Position::new(line, current.chars().count() as u32);
+    TextEdit::new(Range::new(end, end), format!("\n{}{}", indent, text))
+}
+
+/// find a word on a line, preferring occurrences at or after a column
+fn find_word(rope: &Rope, line: u32, col: u32, word: &str) -> Option<Range> {
+    let text = line_text(rope, line);
+    let re = Regex::new(&format!(r"\b{}\b", regex::escape(word))).ok()?;
+    let matches: Vec<(usize, usize)> = re
+        .find_iter(&text)
+        .map(|x| {
+            (
+                text[..x.start()].chars().count(),
+                text[..x.end()].chars().count(),
+            )
+        })
+        .collect();
+    let (start, end) = matches
+        .iter()
+        .find(|(start, _)| *start >= col as usize)
+        .or_else(|| matches.first())?;
+    Some(Range::new(
+        Position::new(line, *start as u32),
+        Position::new(line, *end as u32),
+    ))
+}
+
+/// the quoted name in a message, ex. "creating implicitly: 'foo'"
+fn quoted(message: &str, re: &Regex) -> Option<String> {
+    Some(re.captures(message)?.name("name")?.as_str().to_owned())
+}
+
+/// add a default item before the endcase of the flagged case statement
+fn case_default(uri: &Url, rope: &Rope, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\b(?:case|casex|casez|randcase|endcase)\b").unwrap());
+    let start = rope.pos_to_char(&diagnostic.range.start);
+    let text = rope.slice(start..).to_string();
+    let mut depth = 0;
+    let mut endcase: Option<usize> = None;
+    for token in re.find_iter(&text) {
+        if token.as_str() == "endcase" {
+            depth -= 1;
+            if depth <= 0 {
+                endcase = Some(start + text[..token.start()].chars().count());
+                break;
+            }
+        } else {
+            depth += 1;
+        }
+    }
+    let pos = rope.char_to_pos(endcase?);
+    let line = line_text(rope, pos.line);
+    let before = &line[..line
+        .char_indices()
+        .nth(pos.character as usize)
+        .map_or(line.len(), |x| x.0)];
+    let edit = if before.trim().is_empty() {
+        // indent like the case item above the endcase
+        let indent = (0..pos.line)
+            .rev()
+            .map(|x| line_text(rope, x))
+            .find(|x| !x.trim().is_empty())
+            .map(|x| indentation(&x))
+            .unwrap_or_default();
+        TextEdit::new(
+            Range::new(Position::new(pos.line, 0), Position::new(pos.line, 0)),
+            format!("{}default: ;\n", indent),
+        )
+    } else {
+        TextEdit::new(Range::new(pos, pos), "default: ; ".to_owned())
+    };
+    Some(quick_fix(
+        "Add missing default to case".to_owned(),
+        diagnostic,
+        uri,
+        vec![edit],
+    ))
+}
+
+/// declare a net verilator created implicitly
+fn declare_net(uri: &Url, rope: &Rope, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"creating implicitly: '(?P<name>[^']+)'").unwrap());
+    let name = quoted(&diagnostic.message, re)?;
+    let mut action = quick_fix(
+        format!("Declare wire '{}'", name),
+        diagnostic,
+        uri,
+        vec![insert_before_line(
+            rope,
+            diagnostic.range.start.line,
+            &format!("wire {};", name),
+        )],
+    );
+    action.is_preferred = Some(true);
+    Some(action)
+}
+
+/// disallow implicit nets for the whole file
+fn default_nettype(uri: &Url, rope: &Rope, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    if rope.to_string().contains("`default_nettype") {
+        return None;
+    }
+    Some(quick_fix(
+        "Add `default_nettype none".to_owned(),
+        diagnostic,
+        uri,
+        vec![TextEdit::new(
+            Range::new(Position::new(0, 0), Position::new(0, 0)),
+            "`default_nettype none\n".to_owned(),
+        )],
+    ))
+}
+
+/// cast the mismatched operand to the expected width, Verilog files have no casts so the
+/// operand is zero extended or sliced instead
+fn width_cast(
+    uri: &Url,
+    rope: &Rope,
+    diagnostic: &Diagnostic,
+    extensions: &HashMap<String, LanguageStandard>,
+) -> Option<CodeAction> {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(
+            r"expects (?P<width>\d+) bits.*'(?P<name>[A-Za-z_][\w$]*)' generates (?P<actual>\d+) bits",
+        )
+        .unwrap()
+    });
+    let caps = re.captures(&diagnostic.message)?;
+    let name = caps.name("name")?.as_str();
+    let width: usize = caps.name("width")?.as_str().parse().ok()?;
+    let actual: usize = caps.name("actual")?.as_str().parse().ok()?;
+    let range = find_word(
+        rope,
+        diagnostic.range.start.line,
+        diagnostic.range.start.character,
+        name,
+    )?;
+    let system_verilog = uri
+        .to_file_path()
+        .ok()
+        .and_then(|path| language(extensions, &path))
+        .is_some_and(|x| x >= LanguageStandard::SystemVerilog2005);
+    let (title, text) = if system_verilog {
+        (
+            format!("Cast '{}' to {} bits", name, width),
+            format!("{}'({})", width, name),
+        )
+    } else if width > actual {
+        (
+            format!("Zero extend '{}' to {} bits", name, width),
+            format!("{{{{{}{{1'b0}}}}, {}}}", width - actual, name),
+        )
+    } else if width > 0 && width < actual {
+        (
+            format!("Truncate '{}' to {} bits", name, width),
+            format!("{}[{}:0]", name, width - 1),
+        )
+    } else {
+        return None;
+    };
+    Some(quick_fix(
+        title,
+        diagnostic,
+        uri,
+        vec![TextEdit::new(range, text)],
+    ))
+}
+
+/// make the declaration name and file name match, by renaming either one
+fn rename_declaration(uri: &Url, rope: &Rope, diagnostic: &Diagnostic) -> Vec<CodeAction> {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(r"Filename '(?P<file>[^']+)' does not match \w+ name: '(?P<name>[^']+)'")
+            .unwrap()
+    });
+    let mut fixes: Vec<CodeAction> = Vec::new();
+    let (file, name) = match re.captures(&diagnostic.message) {
+        Some(caps) => (caps["file"].to_owned(), caps["name"].to_owned()),
+        None => return fixes,
+    };
+    if let Some(range) = find_word(
+        rope,
+        diagnostic.range.start.line,
+        diagnostic.range.start.character,
+        &name,
+    ) {
+        fixes.push(quick_fix(
+            format!("Rename '{}' to '{}'", name, file),
+            diagnostic,
+            uri,
+            vec![TextEdit::new(range, file)],
+        ));
+    }
+    let new_uri = uri.to_file_path().ok().and_then(|path| {
+        let new_path = match path.extension() {
+            Some(ext) => path.with_file_name(Path::new(&name).with_extension(ext)),
+            None => path.with_file_name(&name),
+        };
+        Url::from_file_path(new_path).ok()
+    });
+    if let Some(new_uri) = new_uri {
+        fixes.push(CodeAction {
+            title: format!("Rename file to match '{}'", name),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit {
+                document_changes: Some(DocumentChanges::Operations(vec![
+                    DocumentChangeOperation::Op(ResourceOp::Rename(RenameFile {
+                        old_uri: uri.clone(),
+                        new_uri,
+                        options: None,
+                        annotation_id: None,
+                    })),
+                ])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+    }
+    fixes
+}
+
+/// add a directory to source_dirs in the project config of the workspace root, creating the
+/// config if there is none
+fn source_dir_fix(root: &Path, dir: &Path, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    let entry = dir.strip_prefix(root).unwrap_or(dir).to_str()?.to_owned();
+    let title = format!("Add '{}' to source_dirs", entry);
+    let config = root
+        .ancestors()
+        .flat_map(|x| [x.join("veridian.yaml"), x.join("veridian.yml")])
+        .find(|x| x.exists());
+    let edit = match config {
+        Some(config) => {
+            let text = Rope::from_str(&fs::read_to_string(&config).ok()?);
+            WorkspaceEdit {
+                changes: Some(HashMap::from([(
+                    Url::from_file_path(config).ok()?,
+                    vec![source_dirs_edit(&text, &entry)?],
+                )])),
+                ..Default::default()
+            }
+        }
+        None => {
+            let config = Url::from_file_path(root.join("veridian.yml")).ok()?;
+            WorkspaceEdit {
+                document_changes: Some(DocumentChanges::Operations(vec![
+                    DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                        uri: config.clone(),
+                        options: None,
+                        annotation_id: None,
+                    })),
+                    DocumentChangeOperation::Edit(TextDocumentEdit {
+                        text_document: OptionalVersionedTextDocumentIdentifier {
+                            uri: config,
+                            version: None,
+                        },
+                        edits: vec![OneOf::Left(source_dirs_edit(&Rope::new(), &entry)?)],
+                    }),
+                ])),
+                ..Default::default()
+            }
+        }
+    };
+    Some(CodeAction {
+        title: title.clone(),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(edit),
+        command: Some(Command::new(
+            title,
+            ADD_SOURCE_DIR.to_owned(),
+            Some(vec![Value::String(dir.to_str()?.to_owned())]),
+        )),
+        ..Default::default()
+    })
+}
+
+/// append an entry to the source_dirs list of a yaml config, None if the list is written
+/// inline, ex. `source_dirs: [src]`
+fn source_dirs_edit(config: &Rope, entry: &str) -> Option<TextEdit> {
+    let lines: Vec<String> = (0..config.len_lines())
+        .map(|x| line_text(config, x as u32))
+        .collect();
+    let Some(key) = lines.iter().position(|x| x.starts_with("source_dirs:")) else {
+        // add the key at the end of the file
+        let end = config.char_to_pos(config.len_chars());
+        let newline = if end.character == 0 { "" } else { "\n" };
+        return Some(TextEdit::new(
+            Range::new(end, end),
+            format!("{}source_dirs:\n  - {}\n", newline, entry),
+        ));
+    };
+    if !lines[key]["source_dirs:".len()..].trim().is_empty() {
+        return None;
+    }
+    // the list items are indented or start with a dash
+    let items: Vec<usize> = (key + 1..lines.len())
+        .take_while(|x| {
+            lines[*x].trim().is_empty()
+                || lines[*x].starts_with(char::is_whitespace)
+                || lines[*x].starts_with('-')
+        })
+        .filter(|x| lines[*x].trim_start().starts_with('-'))
+        .collect();
+    let (last, prefix) = match (items.first(), items.last()) {
+        (Some(first), Some(last)) => {
+            let item = &lines[*first];
+            let dash = item.find('-')?;
+            let space = item[dash + 1..].len() - item[dash + 1..].trim_start().len();
+            (*last, item[..dash + 1 + space].to_owned())
+        }
+        _ => (key, "  - ".to_owned()),
+    };
+    let end = Position::new(last as u32, lines[last].chars().count() as u32);
+    Some(TextEdit::new(
+        Range::new(end, end),
+        format!("\n{}{}", prefix, entry),
+    ))
+}
+
+/// wrap the flagged line in verilator lint_off/lint_on comments
+fn lint_off(uri: &Url, rope: &Rope, diagnostic: &Diagnostic, code: &str) -> CodeAction {
+    let line = diagnostic.range.start.line;
+    quick_fix(
+        format!("Disable {} with verilator lint_off", code),
+        diagnostic,
+        uri,
+        vec![
+            insert_before_line(rope, line, &format!("// verilator lint_off {}", code)),
+            insert_after_line(rope, line, &format!("// verilator lint_on {}", code)),
+        ],
+    )
+}
+
+/// waive the diagnostic with a suppression comment, works for every linter
+fn disable_next_line(uri: &Url, rope: &Rope, diagnostic: &Diagnostic, code: &str) -> CodeAction {
+    quick_fix(
+        format!("Disable {} for this line", code),
+        diagnostic,
+        uri,
+        vec![insert_before_line(
+            rope,
+            diagnostic.range.start.line,
+            &format!("// veridian-disable-next-line {}", code),
+        )],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::default_extensions;
+
+    fn diagnostic(line: u32, col: u32, code: &str, message: &str) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(line, col), Position::new(line, col)),
+            code: Some(NumberOrString::String(code.to_owned())),
+            source: Some("verilator".to_owned()),
+            message: message.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn edits(action: &CodeAction) -> Vec<TextEdit> {
+        action
+            .edit
+            .as_ref()
+            .unwrap()
+            .changes
+            .as_ref()
+            .unwrap()
+            .values()
+            .next()
+            .unwrap()
+            .clone()
+    }
+
+    fn apply(rope: &Rope, edits: Vec<TextEdit>) -> String {
+        let mut rope = rope.clone();
+        for edit in edits.into_iter().rev() {
+            rope.apply_change(&TextDocumentContentChangeEvent {
+                range: Some(edit.range),
+                range_length: None,
+                text: edit.new_text,
+            });
+        }
+        rope.to_string()
+    }
+
+    #[test]
+    fn test_case_default() {
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let rope = Rope::from_str(
+            r#"module test;
+  always_comb begin
+    case (sel)
+      2'b00: y = a;
+      2'b01: case (x) 1'b0: y = b; endcase
+    endcase
+  end
+endmodule
+"#,
+        );
+        let diag = diagnostic(2, 4, "CASEINCOMPLETE", "Case values incompletely covered");
+        let fixes = quick_fixes(&uri, &rope, &diag, &default_extensions());
+        assert_eq!(fixes[0].title, "Add missing default to case");
+        assert_eq!(
+            apply(&rope, edits(&fixes[0])),
+            r#"module test;
+  always_comb begin
+    case (sel)
+      2'b00: y = a;
+      2'b01: case (x) 1'b0: y = b; endcase
+      default: ;
+    endcase
+  end
+endmodule
+"#
+        );
+        let titles: Vec<&str> = fixes.iter().map(|x| x.title.as_str()).collect();
+        assert_eq!(
+            titles[1..],
+            [
+                "Disable CASEINCOMPLETE with verilator lint_off",
+                "Disable CASEINCOMPLETE for this line"
+            ]
+        );
+        assert_eq!(
+            apply(&rope, edits(&fixes[1])).lines().nth(2),
+            Some("    // verilator lint_off CASEINCOMPLETE")
+        );
+    }
+
+    #[test]
+    fn test_case_default_rule() {
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let rope = Rope::from_str(
+            "module test;\n  always_comb\n    case (sel) 1'b0: y = a; endcase\nendmodule\n",
+        );
+        let diag = Diagnostic {
+            source: Some("veridian".to_owned()),
+            ..diagnostic(2, 4, "missing-case-default", "`case` without `default`")
+        };
+        let fixes = quick_fixes(&uri, &rope, &diag, &default_extensions());
+        let titles: Vec<&str> = fixes.iter().map(|x| x.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            [
+                "Add missing default to case",
+                "Disable missing-case-default for this line"
+            ]
+        );
+        assert_eq!(
+            apply(&rope, edits(&fixes[0])).lines().nth(2),
+            Some("    case (sel) 1'b0: y = a; default: ; endcase")
+        );
+    }
+
+    #[test]
+    fn test_implicit_and_width() {
+        let uri = Url::parse("file:///test.sv").unwrap();
+        let rope = Rope::from_str("module test(input [3:0] b);\n  logic [7:0] a;\n  assign a = b;\n  sub s(.o(c));\nendmodule\n");
+        let diag = diagnostic(
+            3,
+            10,
+            "IMPLICIT",
+            "Signal definition not found, creating implicitly: 'c'",
+        );
+        let fixes = quick_fixes(&uri, &rope, &diag, &default_extensions());
+        assert_eq!(
+            apply(&rope, edits(&fixes[0])).lines().nth(3),
+            Some("  wire c;")
+        );
+        assert!(apply(&rope, edits(&fixes[1])).starts_with("`default_nettype none\nmodule"));
+
+        let diag = diagnostic(
+            2,
+            11,
+            "WIDTH",
+            "Operator ASSIGNW expects 8 bits on the Assign RHS, but Assign RHS's VARREF 'b' generates 4 bits.",
+        );
+        let fixes = quick_fixes(&uri, &rope, &diag, &default_extensions());
+        assert_eq!(
+            apply(&rope, edits(&fixes[0])).lines().nth(2),
+            Some("  assign a = 8'(b);")
+        );
+
+        // Verilog has no casts
+        let uri = Url::parse("file:///test.v").unwrap();
+        let fixes = quick_fixes(&uri, &rope, &diag, &default_extensions());
+        assert_eq!(fixes[0].title, "Zero extend 'b' to 8 bits");
+        assert_eq!(
+            apply(&rope, edits(&fixes[0])).lines().nth(2),
+            Some("  assign a = {{4{1'b0}}, b};")
+        );
+        let diag = diagnostic(
+            2,
+            11,
+            "WIDTH",
+            "Operator ASSIGNW expects 2 bits on the Assign RHS, but Assign RHS's VARREF 'b' generates 4 bits.",
+        );
+        let fixes = quick_fixes(&uri, &rope, &diag, &default_extensions());
+        assert_eq!(
+            apply(&rope, edits(&fixes[0])).lines().nth(2),
+            Some("  assign a = b[1:0];")
+        );
+    }
+
+    #[test]
+    fn test_declfilename() {
+        let uri = Url::parse("file:///src/test.sv").unwrap();
+        let rope = Rope::from_str("module top;\nendmodule\n");
+        let diag = diagnostic(
+            0,
+            7,
+            "DECLFILENAME",
+            "Filename 'test' does not match MODULE name: 'top'",
+        );
+        let fixes = quick_fixes(&uri, &rope, &diag, &default_extensions());
+        assert_eq!(apply(&rope, edits(&fixes[0])), "module test;\nendmodule\n");
+        assert_eq!(
+            fixes[1].edit.as_ref().unwrap().document_changes,
+            Some(DocumentChanges::Operations(vec![
+                DocumentChangeOperation::Op(ResourceOp::Rename(RenameFile {
+                    old_uri: uri,
+                    new_uri: Url::parse("file:///src/top.sv").unwrap(),
+                    options: None,
+                    annotation_id: None,
+                }))
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_rename_convention() {
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test_rename_convention.sv").unwrap();
+        let text = "module test;\n  logic dataIn;\n  assign dataIn = 1'b1;\nendmodule\n";
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri.clone(),
+                "systemverilog".to_owned(),
+                1,
+                text.to_owned(),
+            ),
+        });
+        let mut diag = diagnostic(1, 8, "naming-convention", "");
+        diag.range.end.character = 14;
+        diag.data = Some(Value::String("data_in".to_owned()));
+        let fix = server.rename_convention(&uri, &diag).unwrap();
+        assert_eq!(fix.title, "Rename to 'data_in'");
+        assert_eq!(
+            apply(&Rope::from_str(text), edits(&fix)),
+            "module test;\n  logic data_in;\n  assign data_in = 1'b1;\nendmodule\n"
+        );
+        diag.data = None;
+        assert!(server.rename_convention(&uri, &diag).is_none());
+    }
+
+    #[test]
+    fn test_rename_convention_port() {
+        let server = LSPServer::new(None);
+        let leaf_uri = Url::parse("file:///rename_leaf.sv").unwrap();
+        let top_uri = Url::parse("file:///rename_top.sv").unwrap();
+        let leaf = "module leaf(input logic dataIn);\nendmodule\n";
+        let top = "module top;\n  logic a;\n  leaf u_leaf(.dataIn(a));\nendmodule\n";
+        for (uri, text) in [(&leaf_uri, leaf), (&top_uri, top)] {
+            server.did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    "systemverilog".to_owned(),
+                    1,
+                    text.to_owned(),
+                ),
+            });
+        }
+        let mut diag = diagnostic(0, 24, "naming-convention", "");
+        diag.range.end.character = 30;
+        diag.data = Some(Value::String("data_in".to_owned()));
+        let fix = server.rename_convention(&leaf_uri, &diag);
+        // a port is connected in other files, so it is only renamed with references from the
+        // elaborated design
+        #[cfg(not(feature = "veridian_slang"))]
+        assert!(fix.is_none());
+        #[cfg(feature = "veridian_slang")]
+        {
+            let changes = fix.unwrap().edit.unwrap().changes.unwrap();
+            assert_eq!(
+                apply(&Rope::from_str(leaf), changes[&leaf_uri].clone()),
+                "module leaf(input logic data_in);\nendmodule\n"
+            );
+            assert_eq!(
+                apply(&Rope::from_str(top), changes[&top_uri].clone()),
+                "module top;\n  logic a;\n  leaf u_leaf(.data_in(a));\nendmodule\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_add_source_dir() {
+        let root = tempdir::TempDir::new("add_source_dir").unwrap();
+        let server = LSPServer::new(None);
+        *server.root.write().unwrap() = Some(root.path().to_path_buf());
+        // a parsed file and one that can only be searched as text
+        for (path, text) in [
+            (
+                "lib/fifo/fifo.sv",
+                "module fifo #(parameter W = 8);\nendmodule\n",
+            ),
+            ("lib/ram/ram.sv", "module ram(\nendmodule\n"),
+        ] {
+            server.srcs.add(TextDocumentItem::new(
+                Url::from_file_path(root.path().join(path)).unwrap(),
+                "systemverilog".to_owned(),
+                1,
+                text.to_owned(),
+            ));
+        }
+        server.srcs.wait_indexed();
+        let rope = Rope::from_str("module top;\n  fifo u_fifo();\n  ram u_ram();\nendmodule\n");
+        let mut diag = diagnostic(1, 2, "unresolved-module", "unknown module `fifo`");
+        diag.range.end.character = 6;
+        let fix = server.add_source_dir(&rope, &diag).unwrap();
+        assert_eq!(fix.title, "Add 'lib/fifo' to source_dirs");
+        assert_eq!(
+            fix.command.unwrap().arguments,
+            Some(vec![Value::String(
+                root.path().join("lib/fifo").to_str().unwrap().to_owned()
+            )])
+        );
+        // without a config one is created in the workspace root
+        match fix.edit.unwrap().document_changes {
+            Some(DocumentChanges::Operations(ops)) => match &ops[0] {
+                DocumentChangeOperation::Op(ResourceOp::Create(x)) => assert_eq!(
+                    x.uri,
+                    Url::from_file_path(root.path().join("veridian.yml")).unwrap()
+                ),
+                _ => panic!("expected a config to be created"),
+            },
+            _ => panic!("expected a config to be created"),
+        }
+        let mut diag = diagnostic(2, 2, "unresolved-module", "unknown module `ram`");
+        diag.range.end.character = 5;
+        let fix = server.add_source_dir(&rope, &diag).unwrap();
+        assert_eq!(fix.title, "Add 'lib/ram' to source_dirs");
+        // a name that isn't declared in the workspace, or in a source dir already
+        diag.range.end.character = 4;
+        assert!(server.add_source_dir(&rope, &diag).is_none());
+        server
+            .srcs
+            .source_dirs
+            .write()
+            .unwrap()
+            .push(root.path().join("lib/fifo"));
+        let mut diag = diagnostic(1, 2, "unresolved-module", "unknown module `fifo`");
+        diag.range.end.character = 6;
+        assert!(server.add_source_dir(&rope, &diag).is_none());
+
+        let config =
+            Rope::from_str("include_dirs:\n  - inc\nsource_dirs:\n    - src\n\nlog_level: Info\n");
+        assert_eq!(
+            apply(&config, vec![source_dirs_edit(&config, "lib").unwrap()]),
+            "include_dirs:\n  - inc\nsource_dirs:\n    - src\n    - lib\n\nlog_level: Info\n"
+        );
+        let config = Rope::from_str("log_level: Info");
+        assert_eq!(
+            apply(&config, vec![source_dirs_edit(&config, "lib").unwrap()]),
+            "log_level: Info\nsource_dirs:\n  - lib\n"
+        );
+        assert!(source_dirs_edit(&Rope::from_str("source_dirs: [src]\n"), "lib").is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::server::LSPServer;
+use crate::server::{LSPServer, LanguageStandard};
 use crate::sources::LSPSupport;
 use log::{debug, trace};
 use ropey::{Rope, RopeSlice};
@@ -7,6 +7,8 @@ use tower_lsp::lsp_types::*;
 
 pub mod keyword;
 
+use keyword::keyword_standard;
+
 impl LSPServer {
     pub fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
         debug!("completion requested");
@@ -32,11 +34,14 @@ impl LSPServer {
                         context.trigger_character.clone()?.as_str()
                     );
                     match context.trigger_character?.as_str() {
-                        "." => Some(self.srcs.get_dot_completions(
-                            token.trim_end_matches('.'),
-                            file.text.pos_to_byte(&doc.position),
-                            &doc.text_document.uri,
-                        )?),
+                        "." => match param_override_unit(&file.text, &doc.position) {
+                            Some(unit) => self.srcs.get_param_completions(&unit),
+                            None => Some(self.srcs.get_dot_completions(
+                                token.trim_end_matches('.'),
+                                file.text.pos_to_byte(&doc.position),
+                                &doc.text_document.uri,
+                            )?),
+                        },
                         "$" => Some(CompletionList {
                             is_incomplete: false,
                             items: self.sys_tasks.clone(),
@@ -57,13 +62,9 @@ impl LSPServer {
                         &doc.text_document.uri,
                     )?;
                     // complete keywords
-                    comps.items.extend::<Vec<CompletionItem>>(
-                        self.key_comps
-                            .iter()
-                            .filter(|x| x.label.starts_with(&token))
-                            .cloned()
-                            .collect(),
-                    );
+                    comps
+                        .items
+                        .append(&mut self.keywords(&doc.text_document.uri, &token));
                     Some(comps)
                 }
                 _ => None,
@@ -71,11 +72,14 @@ impl LSPServer {
             None => {
                 let trigger = prev_char(&file.text, &doc.position);
                 match trigger {
-                    '.' => Some(self.srcs.get_dot_completions(
-                        token.trim_end_matches('.'),
-                        file.text.pos_to_byte(&doc.position),
-                        &doc.text_document.uri,
-                    )?),
+                    '.' => match param_override_unit(&file.text, &doc.position) {
+                        Some(unit) => self.srcs.get_param_completions(&unit),
+                        None => Some(self.srcs.get_dot_completions(
+                            token.trim_end_matches('.'),
+                            file.text.pos_to_byte(&doc.position),
+                            &doc.text_document.uri,
+                        )?),
+                    },
                     '$' => Some(CompletionList {
                         is_incomplete: false,
                         items: self.sys_tasks.clone(),
@@ -90,13 +94,9 @@ impl LSPServer {
                             file.text.pos_to_byte(&doc.position),
                             &doc.text_document.uri,
                         )?;
-                        comps.items.extend::<Vec<CompletionItem>>(
-                            self.key_comps
-                                .iter()
-                                .filter(|x| x.label.starts_with(&token))
-                                .cloned()
-                                .collect(),
-                        );
+                        comps
+                            .items
+                            .append(&mut self.keywords(&doc.text_document.uri, &token));
                         Some(comps)
                     }
                 }
@@ -105,6 +105,20 @@ impl LSPServer {
         // eprintln!("comp response: {}", now.elapsed().as_millis());
         Some(CompletionResponse::List(response?))
     }
+
+    /// keywords starting with a token, of the language standard of the file
+    fn keywords(&self, uri: &Url, token: &str) -> Vec<CompletionItem> {
+        let standard = uri
+            .to_file_path()
+            .ok()
+            .and_then(|x| self.conf.read().ok()?.language(&x))
+            .unwrap_or(LanguageStandard::SystemVerilog2017);
+        self.key_comps
+            .iter()
+            .filter(|x| x.label.starts_with(token) && keyword_standard(&x.label) <= standard)
+            .cloned()
+            .collect()
+    }
 }
 
 /// get the previous non-whitespace character
@@ -123,6 +137,55 @@ fn prev_char(text: &Rope, pos: &Position) -> char {
     }
 }
 
+/// the module being instantiated if the position is inside its parameter overrides,
+/// ex. `fifo #(.W(8), .` gives `fifo`
+fn param_override_unit(text: &Rope, pos: &Position) -> Option<String> {
+    let end = text.pos_to_char(pos);
+    // an instantiation starts after the previous statement
+    let start = (0..end)
+        .rev()
+        .find(|x| text.char(*x) == ';')
+        .map_or(0, |x| x + 1);
+    let segment = text.slice(start..end).to_string();
+    // the innermost parenthesis still open at the position
+    let mut open: Vec<usize> = Vec::new();
+    for (idx, c) in segment.char_indices() {
+        match c {
+            '(' => open.push(idx),
+            ')' => {
+                open.pop();
+            }
+            _ => (),
+        }
+    }
+    let before = segment[..*open.last()?]
+        .trim_end()
+        .strip_suffix('#')?
+        .trim_end();
+    let unit: String = before
+        .chars()
+        .rev()
+        .take_while(|x| x.is_alphanumeric() || *x == '_')
+        .collect::<Vec<char>>()
+        .into_iter()
+        .rev()
+        .collect();
+    // the parameter port list of a declaration, not an instantiation
+    let keyword = before[..before.len() - unit.len()]
+        .split_whitespace()
+        .last()
+        .unwrap_or_default();
+    if unit.is_empty()
+        || matches!(
+            keyword,
+            "module" | "macromodule" | "interface" | "program" | "class" | "checker"
+        )
+    {
+        return None;
+    }
+    Some(unit)
+}
+
 /// attempt to get the token the user was trying to complete, by
 /// filtering out characters unneeded for name resolution
 fn get_completion_token(text: &Rope, line: RopeSlice, pos: Position) -> String {
@@ -184,6 +247,7 @@ mod tests {
     use crate::sources::{parse, LSPSupport};
     use crate::support::test_init;
     use ropey::Rope;
+    use std::collections::HashMap;
 
     #[test]
     fn test_get_completion_token() {
@@ -227,6 +291,32 @@ mod tests {
         assert_eq!(&result, "cde");
     }
 
+    #[test]
+    fn test_keyword_standard() {
+        test_init();
+        let server = LSPServer::new(None);
+        let labels = |uri: &str, token: &str| {
+            server
+                .keywords(&Url::parse(uri).unwrap(), token)
+                .into_iter()
+                .map(|x| x.label)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            labels("file:///test.sv", "inter"),
+            vec!["interconnect", "interface", "intersect"]
+        );
+        assert!(labels("file:///test.v", "inter").is_empty());
+        assert_eq!(labels("file:///test.v", "gen"), vec!["generate", "genvar"]);
+        server
+            .conf
+            .write()
+            .unwrap()
+            .extensions
+            .insert("v".to_owned(), LanguageStandard::Verilog1995);
+        assert!(labels("file:///test.v", "gen").is_empty());
+    }
+
     #[test]
     fn test_completion() {
         test_init();
@@ -658,7 +748,7 @@ endinterface
 
         let doc = Rope::from_str(text);
         let url = Url::parse("file:///test.sv").unwrap();
-        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new(), &HashMap::new()).unwrap();
         let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
         let pos = Position::new(8, 9);
         let token = get_completion_token(&doc, doc.line(pos.line as usize), pos);
@@ -697,7 +787,7 @@ endinterface
 
             let doc = Rope::from_str(&text);
             let url = Url::parse("file:///test.sv").unwrap();
-            let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+            let syntax_tree = parse(&doc, &url, &None, &Vec::new(), &HashMap::new()).unwrap();
             let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
             dbg!(&scope_tree);
             /*
@@ -778,4 +868,37 @@ endinterface"#;
             panic!();
         }
     }
+
+    #[test]
+    fn test_param_completion() {
+        test_init();
+        let text = r#"module fifo #(parameter W = 8, localparam D = 2 * W) (input logic clk);
+  parameter int DEPTH = 4;
+endmodule
+module top #(parameter N = 1) ();
+  fifo #(.W(N), .
+  fifo u_a(.
+endmodule
+"#;
+        let doc = Rope::from_str(text);
+        let url = Url::parse("file:///test.sv").unwrap();
+        assert_eq!(
+            param_override_unit(&doc, &Position::new(4, 17)),
+            Some("fifo".to_owned())
+        );
+        assert_eq!(param_override_unit(&doc, &Position::new(5, 12)), None);
+        assert_eq!(param_override_unit(&doc, &Position::new(3, 20)), None);
+        let srcs = crate::sources::Sources::new();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new(), &HashMap::new());
+        let scope_tree = get_scopes(&syntax_tree.unwrap(), &url);
+        *srcs.scope_tree.write().unwrap() = scope_tree;
+        let labels: Vec<String> = srcs
+            .get_param_completions("fifo")
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|x| x.label)
+            .collect();
+        assert_eq!(labels, vec!["W"]);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::server::LanguageStandard;
 use tower_lsp::lsp_types::*;
 
 pub fn keyword_completions(keywords: &[(&str, &str)]) -> Vec<CompletionItem> {
@@ -284,6 +285,184 @@ pub const KEYWORDS: &[(&str, &str)] = &[
     ("xor", ""),
 ];
 
+/// the keywords of Verilog-1995
+const VERILOG_1995: &[&str] = &[
+    "always",
+    "and",
+    "assign",
+    "begin",
+    "buf",
+    "bufif0",
+    "bufif1",
+    "case",
+    "casex",
+    "casez",
+    "cmos",
+    "deassign",
+    "default",
+    "defparam",
+    "disable",
+    "edge",
+    "else",
+    "end",
+    "endcase",
+    "endfunction",
+    "endmodule",
+    "endprimitive",
+    "endspecify",
+    "endtable",
+    "endtask",
+    "event",
+    "for",
+    "force",
+    "forever",
+    "fork",
+    "function",
+    "highz0",
+    "highz1",
+    "if",
+    "ifnone",
+    "initial",
+    "inout",
+    "input",
+    "integer",
+    "join",
+    "large",
+    "macromodule",
+    "medium",
+    "module",
+    "nand",
+    "negedge",
+    "nmos",
+    "nor",
+    "not",
+    "notif0",
+    "notif1",
+    "or",
+    "output",
+    "parameter",
+    "pmos",
+    "posedge",
+    "primitive",
+    "pull0",
+    "pull1",
+    "pulldown",
+    "pullup",
+    "rcmos",
+    "real",
+    "realtime",
+    "reg",
+    "release",
+    "repeat",
+    "rnmos",
+    "rpmos",
+    "rtran",
+    "rtranif0",
+    "rtranif1",
+    "scalared",
+    "small",
+    "specify",
+    "specparam",
+    "strong0",
+    "strong1",
+    "supply0",
+    "supply1",
+    "table",
+    "task",
+    "time",
+    "tran",
+    "tranif0",
+    "tranif1",
+    "tri",
+    "tri0",
+    "tri1",
+    "triand",
+    "trior",
+    "trireg",
+    "vectored",
+    "wait",
+    "wand",
+    "weak0",
+    "weak1",
+    "while",
+    "wire",
+    "wor",
+    "xnor",
+    "xor",
+];
+
+/// the keywords added by Verilog-2001
+const VERILOG_2001: &[&str] = &[
+    "automatic",
+    "cell",
+    "config",
+    "design",
+    "endconfig",
+    "endgenerate",
+    "generate",
+    "genvar",
+    "incdir",
+    "include",
+    "instance",
+    "liblist",
+    "library",
+    "localparam",
+    "noshowcancelled",
+    "pulsestyle_ondetect",
+    "pulsestyle_onevent",
+    "showcancelled",
+    "signed",
+    "unsigned",
+    "use",
+];
+
+/// the keywords added by SystemVerilog-2009
+const SYSTEM_VERILOG_2009: &[&str] = &[
+    "accept_on",
+    "checker",
+    "endchecker",
+    "eventually",
+    "global",
+    "implies",
+    "let",
+    "nexttime",
+    "reject_on",
+    "restrict",
+    "s_always",
+    "s_eventually",
+    "s_nexttime",
+    "s_until",
+    "s_until_with",
+    "strong",
+    "sync_accept_on",
+    "sync_reject_on",
+    "unique0",
+    "until",
+    "until_with",
+    "untyped",
+    "weak",
+];
+
+/// the keywords added by SystemVerilog-2012
+const SYSTEM_VERILOG_2012: &[&str] = &["implements", "interconnect", "nettype", "soft"];
+
+/// the standard that introduced a keyword
+pub fn keyword_standard(keyword: &str) -> LanguageStandard {
+    if VERILOG_1995.contains(&keyword) {
+        LanguageStandard::Verilog1995
+    } else if VERILOG_2001.contains(&keyword) {
+        LanguageStandard::Verilog2001
+    } else if keyword == "uwire" {
+        LanguageStandard::Verilog2005
+    } else if SYSTEM_VERILOG_2009.contains(&keyword) {
+        LanguageStandard::SystemVerilog2009
+    } else if SYSTEM_VERILOG_2012.contains(&keyword) {
+        LanguageStandard::SystemVerilog2012
+    } else {
+        LanguageStandard::SystemVerilog2005
+    }
+}
+
 pub const SYS_TASKS: &[&str] = &[
     "finish",
     "exit",
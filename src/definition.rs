@@ -12,10 +12,18 @@ pub use def_types::*;
 mod extract_defs;
 use extract_defs::*;
 
+#[cfg(feature = "veridian_slang")]
+mod elaborated;
+
 impl LSPServer {
     pub fn goto_definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
         let doc = params.text_document_position_params.text_document.uri;
         let pos = params.text_document_position_params.position;
+        // slang resolves names in the elaborated design, sv-parser is used if it can't
+        #[cfg(feature = "veridian_slang")]
+        if let Some(def) = self.slang_definition(&doc, pos) {
+            return Some(GotoDefinitionResponse::Scalar(def));
+        }
         let file_id = self.srcs.get_id(&doc).to_owned();
         self.srcs.wait_parse_ready(file_id, false);
         let file = self.srcs.get_file(file_id)?;
@@ -38,6 +46,10 @@ impl LSPServer {
     pub fn hover(&self, params: HoverParams) -> Option<Hover> {
         let doc = params.text_document_position_params.text_document.uri;
         let pos = params.text_document_position_params.position;
+        #[cfg(feature = "veridian_slang")]
+        if let Some(hover) = self.slang_hover(&doc, pos) {
+            return Some(hover);
+        }
         let file_id = self.srcs.get_id(&doc).to_owned();
         self.srcs.wait_parse_ready(file_id, false);
         let file = self.srcs.get_file(file_id)?;
@@ -99,6 +111,30 @@ impl LSPServer {
                 .document_highlights(&uri, &file.text, references, byte_idx),
         )
     }
+
+    pub fn references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let uri = params.text_document_position.text_document.uri.clone();
+        #[cfg(feature = "veridian_slang")]
+        if let Some(locations) = self.slang_references(
+            &uri,
+            params.text_document_position.position,
+            params.context.include_declaration,
+        ) {
+            return Some(locations);
+        }
+        // without an elaborated design references are only found in the document
+        let highlights = self.document_highlight(DocumentHighlightParams {
+            text_document_position_params: params.text_document_position,
+            work_done_progress_params: params.work_done_progress_params,
+            partial_result_params: params.partial_result_params,
+        })?;
+        Some(
+            highlights
+                .into_iter()
+                .map(|x| Location::new(uri.clone(), x.range))
+                .collect(),
+        )
+    }
 }
 
 /// return all identifiers in a syntax tree matching a given token
@@ -354,6 +390,7 @@ mod tests {
     use crate::sources::{parse, LSPSupport};
     use crate::support::test_init;
     use ropey::Rope;
+    use std::collections::HashMap;
     use std::fs::read_to_string;
     use std::path::PathBuf;
 
@@ -373,7 +410,7 @@ mod tests {
         let text = read_to_string(d).unwrap();
         let doc = Rope::from_str(&text);
         let url = Url::parse("file:///test_data/definition_test.sv").unwrap();
-        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new(), &HashMap::new()).unwrap();
         trace!("{}", &syntax_tree);
         let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
         trace!("{:#?}", &scope_tree);
@@ -431,7 +468,7 @@ module test;
 endmodule"#;
         let doc = Rope::from_str(text);
         let url = Url::parse("file:///test.sv").unwrap();
-        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new(), &HashMap::new()).unwrap();
         let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
         let symbol = scope_tree.document_symbols(&url, &doc);
         let symbol = symbol.first().unwrap();
@@ -457,7 +494,7 @@ module test;
 endmodule"#;
         let doc = Rope::from_str(text);
         let url = Url::parse("file:///test.sv").unwrap();
-        let syntax_tree = parse(&doc, &url, &None, &Vec::new()).unwrap();
+        let syntax_tree = parse(&doc, &url, &None, &Vec::new(), &HashMap::new()).unwrap();
         let scope_tree = get_scopes(&syntax_tree, &url).unwrap();
         let references = all_identifiers(&syntax_tree, "clk");
         let highlights = scope_tree.document_highlights(
@@ -496,4 +533,47 @@ endmodule"#;
         ];
         assert_eq!(highlights, expected)
     }
+
+    #[test]
+    fn test_references() {
+        test_init();
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test_references.sv").unwrap();
+        let text = r#"module test;
+  logic clk;
+  assign clk = 1'b1;
+endmodule
+"#;
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri.clone(),
+                "systemverilog".to_owned(),
+                1,
+                text.to_owned(),
+            ),
+        });
+        let locations = server
+            .references(ReferenceParams {
+                text_document_position: TextDocumentPositionParams::new(
+                    TextDocumentIdentifier::new(uri.clone()),
+                    Position::new(2, 10),
+                ),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                context: ReferenceContext {
+                    include_declaration: true,
+                },
+            })
+            .unwrap();
+        assert_eq!(
+            locations,
+            vec![
+                Location::new(
+                    uri.clone(),
+                    Range::new(Position::new(1, 8), Position::new(1, 11))
+                ),
+                Location::new(uri, Range::new(Position::new(2, 9), Position::new(2, 12))),
+            ]
+        );
+    }
 }
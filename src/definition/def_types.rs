@@ -23,6 +23,12 @@ pub fn clean_type_str(type_str: &str, ident: &str) -> String {
         .replace(" : ", ":")
 }
 
+/// whether a parameter is declared as a localparam
+pub fn is_localparam(def: &dyn Definition) -> bool {
+    def.symbol_kind() == SymbolKind::TYPE_PARAMETER
+        && def.type_str().split_whitespace().next() == Some("localparam")
+}
+
 pub fn copy_defs(defs: &[Box<dyn Definition>]) -> Vec<Box<dyn Definition>> {
     let mut decs: Vec<Box<dyn Definition>> = Vec::new();
     for def in defs {
@@ -54,6 +60,7 @@ pub fn copy_scopes(scopes: &[Box<dyn Scope>]) -> Vec<Box<dyn Scope>> {
             def_type: scope.def_type(),
             defs: Vec::new(),
             scopes: Vec::new(),
+            param_ports_end: scope.param_ports_end(),
         };
         scope_copy.defs.extend(copy_defs(scope.defs()));
         scope_copy.scopes.extend(copy_scopes(scope.scopes()));
@@ -107,6 +114,30 @@ pub trait Scope: std::fmt::Debug + Definition + Sync + Send {
             def_type: DefinitionType::GenericScope,
         }
     }
+    // the end byte of the parameter port list, ex. `#(parameter W = 8)`, None if there is none
+    fn param_ports_end(&self) -> Option<usize> {
+        None
+    }
+    // whether a parameter of this scope is local, parameters declared in the body of a scope
+    // with a parameter port list are localparams (IEEE 1800-2017 6.20.1)
+    fn is_local_parameter(&self, def: &dyn Definition) -> bool {
+        is_localparam(def)
+            || (def.symbol_kind() == SymbolKind::TYPE_PARAMETER
+                && self
+                    .param_ports_end()
+                    .is_some_and(|end| def.byte_idx() > end))
+    }
+    // the parameters of this scope that can be overridden when it is instantiated
+    fn parameters(&self) -> Vec<&dyn Definition> {
+        self.defs()
+            .iter()
+            .filter(|x| {
+                x.symbol_kind() == SymbolKind::TYPE_PARAMETER
+                    && !self.is_local_parameter(x.as_ref())
+            })
+            .map(|x| x.as_ref())
+            .collect()
+    }
     /// return a completion from the scope tree, this function should be called on the global scope
     fn get_completion(&self, token: &str, byte_idx: usize, url: &Url) -> Vec<CompletionItem> {
         let mut completions: Vec<CompletionItem> = Vec::new();
@@ -761,6 +792,8 @@ pub struct GenericScope {
     pub def_type: DefinitionType,
     pub defs: Vec<Box<dyn Definition>>,
     pub scopes: Vec<Box<dyn Scope>>,
+    // the end byte of the parameter port list, if there is one
+    pub param_ports_end: Option<usize>,
 }
 
 impl GenericScope {
@@ -777,6 +810,7 @@ impl GenericScope {
             def_type: DefinitionType::GenericScope,
             defs: Vec::new(),
             scopes: Vec::new(),
+            param_ports_end: None,
         }
     }
 
@@ -855,6 +889,10 @@ impl Scope for GenericScope {
     fn scopes(&self) -> &Vec<Box<dyn Scope>> {
         &self.scopes
     }
+
+    fn param_ports_end(&self) -> Option<usize> {
+        self.param_ports_end
+    }
 }
 
 #[derive(Debug)]
@@ -874,6 +912,8 @@ pub struct ClassDec {
     pub extends: (Vec<String>, Option<String>),
     // class, package
     pub implements: Vec<(String, Option<String>)>,
+    // the end byte of the parameter port list, if there is one
+    pub param_ports_end: Option<usize>,
 }
 
 impl ClassDec {
@@ -892,6 +932,7 @@ impl ClassDec {
             scopes: Vec::new(),
             extends: (Vec::new(), None),
             implements: Vec::new(),
+            param_ports_end: None,
         }
     }
 }
@@ -960,4 +1001,8 @@ impl Scope for ClassDec {
     fn scopes(&self) -> &Vec<Box<dyn Scope>> {
         &self.scopes
     }
+
+    fn param_ports_end(&self) -> Option<usize> {
+        self.param_ports_end
+    }
 }
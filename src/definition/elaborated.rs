@@ -0,0 +1,241 @@
+use super::get_hover;
+use crate::diagnostics::slang_design;
+use crate::server::LSPServer;
+use log::debug;
+use ropey::Rope;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tower_lsp::lsp_types::*;
+use veridian_slang::{SlangDesign, SlangLocation, SlangSymbol};
+
+impl LSPServer {
+    /// elaborate the design the document belongs to, the design is only elaborated again once a
+    /// source was added or changed
+    fn elaborate(&self, uri: &Url) -> Option<Arc<SlangDesign>> {
+        // read before the sources, so a change while elaborating invalidates the design
+        let generation = self.srcs.generation.load(Ordering::SeqCst);
+        if let Some((cached_uri, cached_generation, design)) = &*self.design.lock().ok()? {
+            if cached_uri == uri && *cached_generation == generation {
+                return Some(design.clone());
+            }
+        }
+        let file_id = *self.srcs.names.read().ok()?.get(uri)?;
+        let text = self.srcs.get_file(file_id)?.read().ok()?.text.clone();
+        let urls = self.srcs.names.read().ok()?.keys().cloned().collect();
+        let open_files = self.srcs.open_buffers();
+        let design = {
+            let conf = self.conf.read().ok()?;
+            Arc::new(slang_design(uri, &text, urls, &open_files, &conf)?)
+        };
+        *self.design.lock().ok()? = Some((uri.clone(), generation, design.clone()));
+        Some(design)
+    }
+
+    /// the text of a file, from the sources if it is known, otherwise from disk
+    fn source_text(&self, uri: &Url) -> Option<Rope> {
+        if let Some(file_id) = self.srcs.names.read().ok()?.get(uri) {
+            return Some(self.srcs.get_file(*file_id)?.read().ok()?.text.clone());
+        }
+        let text = std::fs::read_to_string(uri.to_file_path().ok()?).ok()?;
+        Some(Rope::from_str(&text))
+    }
+
+    /// goto definition using slang's elaborated design
+    pub fn slang_definition(&self, uri: &Url, pos: Position) -> Option<Location> {
+        let design = self.elaborate(uri)?;
+        let symbol = symbol_at(&design, uri, pos)?;
+        debug!("slang definition: {}", symbol.path);
+        location(&symbol.location)
+    }
+
+    /// hover using slang's elaborated design, showing the resolved type and parameter values
+    pub fn slang_hover(&self, uri: &Url, pos: Position) -> Option<Hover> {
+        let design = self.elaborate(uri)?;
+        let symbol = symbol_at(&design, uri, pos)?;
+        let instances: Vec<&SlangSymbol> = design
+            .symbols
+            .iter()
+            .filter(|x| x.location == symbol.location)
+            .collect();
+        let mut contents = vec![MarkedString::LanguageString(LanguageString {
+            language: "systemverilog".to_owned(),
+            value: declaration(symbol),
+        })];
+        // a declaration elaborated with different values in each instance
+        if instances
+            .iter()
+            .any(|x| x.value != symbol.value || x.type_name != symbol.type_name)
+        {
+            for instance in &instances {
+                let value = if instance.value.is_empty() {
+                    &instance.type_name
+                } else {
+                    &instance.value
+                };
+                contents.push(MarkedString::String(format!(
+                    "`{}`: `{}`",
+                    instance.path, value
+                )));
+            }
+        }
+        let def_uri = location(&symbol.location)?.uri;
+        if let Some(text) = self.source_text(&def_uri) {
+            let line = symbol.location.range.0 .0.saturating_sub(1) as usize;
+            if line < text.len_lines() {
+                contents.push(MarkedString::LanguageString(LanguageString {
+                    language: "systemverilog".to_owned(),
+                    value: get_hover(&text, line),
+                }));
+            }
+        }
+        Some(Hover {
+            contents: HoverContents::Array(contents),
+            range: None,
+        })
+    }
+
+    /// find references using slang's elaborated design
+    pub fn slang_references(
+        &self,
+        uri: &Url,
+        pos: Position,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let design = self.elaborate(uri)?;
+        let symbol = symbol_at(&design, uri, pos)?;
+        let mut locations: Vec<Location> = Vec::new();
+        if include_declaration {
+            locations.push(location(&symbol.location)?);
+        }
+        // a reference is reported once for every instance it is elaborated in
+        for reference in &design.references {
+            if design.symbols[reference.symbol].location == symbol.location {
+                if let Some(loc) = location(&reference.location) {
+                    if !locations.contains(&loc) {
+                        locations.push(loc);
+                    }
+                }
+            }
+        }
+        Some(locations)
+    }
+}
+
+/// the declared symbol at a position, either through a reference or its declaration
+fn symbol_at<'a>(design: &'a SlangDesign, uri: &Url, pos: Position) -> Option<&'a SlangSymbol> {
+    let path = uri.to_file_path().ok()?;
+    design
+        .references
+        .iter()
+        .find(|x| x.location.file == path && contains(&x.location, pos))
+        .and_then(|x| design.symbols.get(x.symbol))
+        .or_else(|| {
+            design
+                .symbols
+                .iter()
+                .find(|x| x.location.file == path && contains(&x.location, pos))
+        })
+}
+
+/// whether a location contains a position, including the position right after it
+fn contains(location: &SlangLocation, pos: Position) -> bool {
+    let ((start_line, start_col), (end_line, end_col)) = location.range;
+    let pos = (pos.line + 1, pos.character + 1);
+    (start_line, start_col) <= pos && pos <= (end_line, end_col)
+}
+
+fn location(location: &SlangLocation) -> Option<Location> {
+    let ((start_line, start_col), (end_line, end_col)) = location.range;
+    Some(Location::new(
+        Url::from_file_path(&location.file).ok()?,
+        Range::new(
+            Position::new(start_line.saturating_sub(1), start_col.saturating_sub(1)),
+            Position::new(end_line.saturating_sub(1), end_col.saturating_sub(1)),
+        ),
+    ))
+}
+
+/// the elaborated declaration of a symbol, ex. `parameter int WIDTH = 8`
+fn declaration(symbol: &SlangSymbol) -> String {
+    match symbol.kind.as_str() {
+        "Parameter" => format!(
+            "parameter {} {} = {}",
+            symbol.type_name, symbol.name, symbol.value
+        ),
+        "Instance" => format!("{} {}", symbol.type_name, symbol.name),
+        "Definition" => symbol.name.clone(),
+        _ => format!("{} {}", symbol.type_name, symbol.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use veridian_slang::SlangReference;
+
+    fn loc(file: &str, line: u32, start: u32, end: u32) -> SlangLocation {
+        SlangLocation {
+            file: PathBuf::from(file),
+            range: ((line, start), (line, end)),
+        }
+    }
+
+    #[test]
+    fn test_symbol_at() {
+        let symbol = |name: &str, path: &str, line| SlangSymbol {
+            name: name.to_owned(),
+            kind: "Parameter".to_owned(),
+            path: path.to_owned(),
+            location: loc("/test.sv", line, 13, 18),
+            type_name: "int".to_owned(),
+            value: "8".to_owned(),
+        };
+        let design = SlangDesign {
+            symbols: vec![
+                symbol("WIDTH", "top.WIDTH", 2),
+                symbol("DEPTH", "top.DEPTH", 3),
+            ],
+            references: vec![SlangReference {
+                location: loc("/test.sv", 5, 10, 15),
+                symbol: 1,
+            }],
+        };
+        let uri = Url::parse("file:///test.sv").unwrap();
+        // on a reference
+        let found = symbol_at(&design, &uri, Position::new(4, 12)).unwrap();
+        assert_eq!(found.path, "top.DEPTH");
+        // on a declaration, including right after the name
+        let found = symbol_at(&design, &uri, Position::new(1, 17)).unwrap();
+        assert_eq!(found.path, "top.WIDTH");
+        assert!(symbol_at(&design, &uri, Position::new(4, 2)).is_none());
+        assert_eq!(declaration(found), "parameter int WIDTH = 8");
+    }
+
+    #[test]
+    fn test_elaborate_cache() {
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///design.sv").unwrap();
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri.clone(),
+                "systemverilog".to_owned(),
+                1,
+                "module top; logic a; endmodule\n".to_owned(),
+            ),
+        });
+        let design = server.elaborate(&uri).unwrap();
+        assert!(Arc::ptr_eq(&design, &server.elaborate(&uri).unwrap()));
+        server.did_change(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(uri.clone(), 2),
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "module top; logic b; endmodule\n".to_owned(),
+            }],
+        });
+        let changed = server.elaborate(&uri).unwrap();
+        assert!(!Arc::ptr_eq(&design, &changed));
+        assert!(changed.symbols.iter().any(|x| x.path == "top.b"));
+    }
+}
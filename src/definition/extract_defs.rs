@@ -735,6 +735,7 @@ pub fn data_dec(
                                 def_type: x.def_type,
                                 defs: copy_defs(&x.defs),
                                 scopes: copy_scopes(&x.scopes),
+                                param_ports_end: None,
                             }));
                         }
                     }
@@ -1474,6 +1475,16 @@ pub fn param_port_list(
     Some(defs)
 }
 
+/// the byte after the closing parenthesis of a parameter port list
+fn param_port_list_end(tree: &SyntaxTree, node: &ParameterPortList) -> usize {
+    let close = match node {
+        ParameterPortList::Assignment(x) => &x.nodes.1.nodes.2,
+        ParameterPortList::Declaration(x) => &x.nodes.1.nodes.2,
+        ParameterPortList::Empty(x) => &x.2,
+    };
+    get_loc(tree, RefNode::Symbol(close)) + 1
+}
+
 pub fn module_dec(
     tree: &SyntaxTree,
     node: &ModuleDeclaration,
@@ -1498,6 +1509,7 @@ pub fn module_dec(
             }
             if let Some(pport_list) = &x.nodes.0.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -1519,6 +1531,7 @@ pub fn module_dec(
             }
             if let Some(pport_list) = &x.nodes.0.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -1570,6 +1583,7 @@ pub fn module_dec(
             }
             if let Some(pport_list) = &x.nodes.1.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -1591,6 +1605,7 @@ pub fn module_dec(
             }
             if let Some(pport_list) = &x.nodes.1.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -1644,6 +1659,7 @@ pub fn interface_dec(
             }
             if let Some(pport_list) = &x.nodes.0.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -1665,6 +1681,7 @@ pub fn interface_dec(
             }
             if let Some(pport_list) = &x.nodes.0.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -1710,6 +1727,7 @@ pub fn interface_dec(
             }
             if let Some(pport_list) = &x.nodes.1.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -1731,6 +1749,7 @@ pub fn interface_dec(
             }
             if let Some(pport_list) = &x.nodes.1.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -2042,6 +2061,7 @@ pub fn program_dec(
             }
             if let Some(pport_list) = &x.nodes.0.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -2064,6 +2084,7 @@ pub fn program_dec(
             }
             if let Some(pport_list) = &x.nodes.0.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -2109,6 +2130,7 @@ pub fn program_dec(
             }
             if let Some(pport_list) = &x.nodes.1.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -2130,6 +2152,7 @@ pub fn program_dec(
             }
             if let Some(pport_list) = &x.nodes.1.nodes.5 {
                 let pports = param_port_list(tree, pport_list, event_iter, url)?;
+                scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
                 for pport in pports {
                     scope.defs.push(Box::new(pport));
                 }
@@ -2230,6 +2253,7 @@ pub fn class_dec(
     advance_until_leave!(type_str, tree, event_iter, RefNode::ClassIdentifier);
     if let Some(pport_list) = &node.nodes.4 {
         let pports = param_port_list(tree, pport_list, event_iter, url)?;
+        scope.param_ports_end = Some(param_port_list_end(tree, pport_list));
         for pport in pports {
             scope.defs.push(Box::new(pport));
         }
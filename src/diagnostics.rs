@@ -1,127 +1,286 @@
-use crate::server::ProjectConfig;
-#[cfg(feature = "slang")]
+#[cfg(feature = "veridian_slang")]
+use crate::server::{language, LanguageStandard};
+use crate::server::{LSPServer, ProjectConfig};
+use linter::custom_linter;
+#[cfg(feature = "veridian_slang")]
+use log::error;
+use overlay::Overlay;
 use path_clean::PathClean;
+use process::run_command;
+pub use process::CancelToken;
 use regex::Regex;
 use ropey::Rope;
-#[cfg(feature = "slang")]
+use severity::apply_severity;
+use std::collections::{HashMap, HashSet};
 use std::env::current_dir;
-#[cfg(feature = "slang")]
-use std::path::Path;
-use std::path::PathBuf;
-use std::process::{Command, Stdio};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use suppress::filter_suppressed;
 use tower_lsp::lsp_types::*;
-#[cfg(feature = "slang")]
-use veridian_slang::slang_compile;
+#[cfg(feature = "veridian_slang")]
+use veridian_slang::{
+    SlangBuffer, SlangDesign, SlangDiagnostic, SlangOptions, SlangSession, SlangSeverity,
+};
 use walkdir::DirEntry;
-#[cfg(feature = "slang")]
+#[cfg(feature = "veridian_slang")]
 use walkdir::WalkDir;
 
-#[cfg(feature = "slang")]
+mod linter;
+mod overlay;
+mod process;
+mod pull;
+mod semantic;
+mod severity;
+mod suppress;
+
+pub use pull::PulledReport;
+pub use semantic::Workspace;
+
 pub fn get_diagnostics(
     uri: Url,
     rope: &Rope,
-    files: Vec<Url>,
+    #[allow(unused_variables)] files: Vec<Url>,
+    open_files: Vec<(Url, Rope)>,
+    semantic: Vec<Diagnostic>,
     conf: &ProjectConfig,
-) -> PublishDiagnosticsParams {
+    cancel: &CancelToken,
+) -> Vec<PublishDiagnosticsParams> {
     if !(cfg!(test) && (uri.to_string().starts_with("file:///test"))) {
-        let paths = get_paths(files, conf.auto_search_workdir);
-        let mut diagnostics = {
+        let mut diagnostics: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+        diagnostics.insert(uri.clone(), semantic);
+        // stage unsaved buffers so tools lint the editor's text
+        let mut overlay = Overlay::new();
+        let path = uri.to_file_path().ok();
+        let staged_path = path.as_ref().map(|x| overlay.stage(x, rope));
+        for (url, text) in &open_files {
+            if url != &uri {
+                if let Ok(open_path) = url.to_file_path() {
+                    overlay.stage(&open_path, text);
+                }
+            }
+        }
+        if let (Some(path), Some(staged_path)) = (&path, &staged_path) {
             if conf.verilator.syntax.enabled {
-                if let Ok(path) = uri.to_file_path() {
-                    verilator_syntax(
-                        rope,
-                        path,
-                        &conf.verilator.syntax.path,
-                        &conf.verilator.syntax.args,
-                    )
-                    .unwrap_or_default()
-                } else {
-                    Vec::new()
+                let mut inc_dirs: Vec<PathBuf> =
+                    path.parent().into_iter().map(Path::to_path_buf).collect();
+                inc_dirs.extend(conf.include_dirs.iter().map(|x| absolute_path(x)));
+                let mut args = conf.verilator.syntax.args.clone();
+                args.extend(defines(conf).iter().map(|x| format!("-D{}", x)));
+                // after the configured args to take precedence over --sv
+                if let Some(standard) = conf.language(path) {
+                    args.push("--default-language".to_owned());
+                    args.push(standard.to_string());
+                }
+                // verilator also reports diagnostics for included files and other modules
+                for (path, mut diags) in verilator_syntax(
+                    rope,
+                    staged_path.clone(),
+                    &overlay.include_dirs(&inc_dirs),
+                    &overlay,
+                    &conf.verilator.syntax.path,
+                    &args,
+                    cancel,
+                )
+                .unwrap_or_default()
+                {
+                    if let Ok(url) = Url::from_file_path(path) {
+                        diagnostics.entry(url).or_default().append(&mut diags);
+                    }
                 }
-            } else if conf.verible.syntax.enabled {
-                verible_syntax(rope, &conf.verible.syntax.path, &conf.verible.syntax.args)
-                    .unwrap_or_default()
-            } else {
-                Vec::new()
             }
-        };
-        diagnostics.append(&mut parse_report(
-            uri.clone(),
-            slang_compile(paths).unwrap(),
-        ));
-        PublishDiagnosticsParams {
-            uri,
-            diagnostics,
-            version: None,
+            diagnostics
+                .entry(uri.clone())
+                .or_default()
+                .append(&mut linter_diagnostics(
+                    staged_path,
+                    rope,
+                    &overlay,
+                    conf,
+                    cancel,
+                ));
         }
+        // verible-verilog-syntax doesn't preprocess, so it takes no defines
+        if !conf.verilator.syntax.enabled && conf.verible.syntax.enabled {
+            diagnostics.entry(uri.clone()).or_default().append(
+                &mut verible_syntax(
+                    rope,
+                    &conf.verible.syntax.path,
+                    &conf.verible.syntax.args,
+                    cancel,
+                )
+                .unwrap_or_default(),
+            );
+        }
+        #[cfg(feature = "veridian_slang")]
+        {
+            let (buffers, paths) = slang_sources(path.as_deref(), rope, files, &open_files, conf);
+            let options = slang_options(path.as_deref(), &overlay, conf);
+            match slang_session().diagnostics(buffers, paths, &options) {
+                Ok(report) => diagnostics
+                    .entry(uri.clone())
+                    .or_default()
+                    .append(&mut parse_report(uri.clone(), report, &overlay)),
+                Err(e) => error!("slang failed: {}", e),
+            }
+        }
+        // apply suppression comments from the file each diagnostic is reported in, then the
+        // configured severities
+        for (url, diags) in diagnostics.iter_mut() {
+            let text = if url == &uri {
+                Some(rope.clone())
+            } else if let Some((_, text)) = open_files.iter().find(|x| &x.0 == url) {
+                Some(text.clone())
+            } else {
+                url.to_file_path()
+                    .ok()
+                    .and_then(|x| fs::read_to_string(x).ok())
+                    .map(|x| Rope::from_str(&x))
+            };
+            *diags = filter_diagnostics(url, text.as_ref(), std::mem::take(diags), conf);
+        }
+        publish_params(uri, diagnostics)
     } else {
-        PublishDiagnosticsParams {
+        vec![PublishDiagnosticsParams {
             uri,
             diagnostics: Vec::new(),
             version: None,
-        }
+        }]
     }
 }
 
-#[cfg(not(feature = "slang"))]
-pub fn get_diagnostics(
-    uri: Url,
-    rope: &Rope,
-    #[allow(unused_variables)] files: Vec<Url>,
+/// apply the suppression comments of the file diagnostics are reported in, then the configured
+/// severities
+pub fn filter_diagnostics(
+    uri: &Url,
+    text: Option<&Rope>,
+    mut diagnostics: Vec<Diagnostic>,
     conf: &ProjectConfig,
-) -> PublishDiagnosticsParams {
-    if !(cfg!(test) && (uri.to_string().starts_with("file:///test"))) {
-        let diagnostics = {
-            if conf.verilator.syntax.enabled {
-                if let Ok(path) = uri.to_file_path() {
-                    verilator_syntax(
-                        rope,
-                        path,
-                        &conf.verilator.syntax.path,
-                        &conf.verilator.syntax.args,
-                    )
-                    .unwrap_or_default()
-                } else {
-                    Vec::new()
-                }
-            } else if conf.verible.syntax.enabled {
-                verible_syntax(rope, &conf.verible.syntax.path, &conf.verible.syntax.args)
-                    .unwrap_or_default()
-            } else {
-                Vec::new()
-            }
-        };
-        PublishDiagnosticsParams {
+) -> Vec<Diagnostic> {
+    if let Some(text) = text {
+        diagnostics = filter_suppressed(text, diagnostics);
+    }
+    apply_severity(
+        uri.to_file_path().ok().as_deref(),
+        diagnostics,
+        &conf.diagnostics,
+    )
+}
+
+/// convert diagnostics grouped by file into publish params, the requested file is always first
+fn publish_params(
+    uri: Url,
+    mut diagnostics: HashMap<Url, Vec<Diagnostic>>,
+) -> Vec<PublishDiagnosticsParams> {
+    let mut params = vec![PublishDiagnosticsParams {
+        diagnostics: diagnostics.remove(&uri).unwrap_or_default(),
+        uri,
+        version: None,
+    }];
+    for (uri, diagnostics) in diagnostics {
+        params.push(PublishDiagnosticsParams {
             uri,
             diagnostics,
             version: None,
+        });
+    }
+    params
+}
+
+impl LSPServer {
+    /// remember which other files received diagnostics while linting a file, and clear the ones
+    /// that are no longer reported
+    pub fn track_published(
+        &self,
+        mut params: Vec<PublishDiagnosticsParams>,
+    ) -> Vec<PublishDiagnosticsParams> {
+        let uri = match params.first() {
+            Some(param) => param.uri.clone(),
+            None => return params,
+        };
+        let current: HashSet<Url> = params[1..]
+            .iter()
+            .filter(|x| !x.diagnostics.is_empty())
+            .map(|x| x.uri.clone())
+            .collect();
+        let mut published = self.published.lock().unwrap();
+        if let Some(previous) = published.get(&uri) {
+            for stale in previous.difference(&current) {
+                if !params.iter().any(|x| &x.uri == stale) {
+                    params.push(PublishDiagnosticsParams::new(
+                        stale.clone(),
+                        Vec::new(),
+                        None,
+                    ));
+                }
+            }
         }
-    } else {
-        PublishDiagnosticsParams {
-            uri,
-            diagnostics: Vec::new(),
-            version: None,
-        }
+        published.insert(uri, current);
+        params
+    }
+}
+
+/// run all user defined linters on a file
+fn linter_diagnostics(
+    path: &Path,
+    rope: &Rope,
+    overlay: &Overlay,
+    conf: &ProjectConfig,
+    cancel: &CancelToken,
+) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let include_dirs = linter_include_dirs(overlay, conf);
+    let defines = defines(conf);
+    for linter in conf.linters.iter().filter(|x| x.enabled) {
+        diagnostics.append(
+            &mut custom_linter(rope, path, &include_dirs, &defines, linter, cancel)
+                .unwrap_or_default(),
+        );
     }
+    diagnostics
+}
+
+/// the include dirs substituted for ${includes}, so unsaved headers in the overlay are found
+/// first
+fn linter_include_dirs(overlay: &Overlay, conf: &ProjectConfig) -> Vec<String> {
+    let dirs: Vec<PathBuf> = conf.include_dirs.iter().map(|x| absolute_path(x)).collect();
+    overlay
+        .include_dirs(&dirs)
+        .iter()
+        .map(|x| x.display().to_string())
+        .collect()
+}
+
+/// the project defines as NAME or NAME=VALUE, sorted so tools are run with the same arguments
+fn defines(conf: &ProjectConfig) -> Vec<String> {
+    let mut defines: Vec<String> = conf
+        .defines
+        .iter()
+        .map(|(name, value)| match value {
+            Some(value) => format!("{}={}", name, value),
+            None => name.clone(),
+        })
+        .collect();
+    defines.sort();
+    defines
 }
 
 /// recursively find source file paths from working directory
 /// and open files
-#[cfg(feature = "slang")]
-fn get_paths(files: Vec<Url>, search_workdir: bool) -> Vec<PathBuf> {
+#[cfg(feature = "veridian_slang")]
+fn get_paths(
+    files: Vec<Url>,
+    search_workdir: bool,
+    extensions: &HashMap<String, LanguageStandard>,
+) -> Vec<PathBuf> {
     // check recursively from working dir for source files
     let mut paths: Vec<PathBuf> = Vec::new();
     if search_workdir {
         let walker = WalkDir::new(".").into_iter();
         for entry in walker.filter_entry(|e| !is_hidden(e)) {
             let entry = entry.unwrap();
-            if entry.file_type().is_file() {
-                let extension = entry.path().extension().unwrap();
-
-                if extension == "sv" || extension == "svh" || extension == "v" || extension == "vh"
-                {
-                    paths.push(entry.path().to_path_buf());
-                }
+            if entry.file_type().is_file() && language(extensions, entry.path()).is_some() {
+                paths.push(entry.path().to_path_buf());
             }
         }
     }
@@ -132,18 +291,10 @@ fn get_paths(files: Vec<Url>, search_workdir: bool) -> Vec<PathBuf> {
             if !paths.contains(&path) {
                 let walker = WalkDir::new(path.parent().unwrap()).into_iter();
                 for entry in walker.filter_entry(|e| !is_hidden(e)).flatten() {
-                    if entry.file_type().is_file() && entry.path().extension().is_some() {
-                        let extension = entry.path().extension().unwrap();
-
-                        if extension == "sv"
-                            || extension == "svh"
-                            || extension == "v"
-                            || extension == "vh"
-                        {
-                            let entry_path = entry.path().to_path_buf();
-                            if !paths.contains(&entry_path) {
-                                paths.push(entry_path);
-                            }
+                    if entry.file_type().is_file() && language(extensions, entry.path()).is_some() {
+                        let entry_path = entry.path().to_path_buf();
+                        if !paths.contains(&entry_path) {
+                            paths.push(entry_path);
                         }
                     }
                 }
@@ -161,42 +312,161 @@ pub fn is_hidden(entry: &DirEntry) -> bool {
         .unwrap_or(false)
 }
 
-#[cfg(feature = "slang")]
-/// parse a report from slang
-fn parse_report(uri: Url, report: String) -> Vec<Diagnostic> {
+/// slang options from the config, include dirs are searched in the overlay first
+#[cfg(feature = "veridian_slang")]
+fn slang_options(path: Option<&Path>, overlay: &Overlay, conf: &ProjectConfig) -> SlangOptions {
+    let mut inc_dirs: Vec<PathBuf> = path
+        .and_then(Path::parent)
+        .into_iter()
+        .map(Path::to_path_buf)
+        .collect();
+    inc_dirs.extend(
+        conf.include_dirs
+            .iter()
+            .chain(conf.slang.include_dirs.iter())
+            .map(|x| absolute_path(x)),
+    );
+    SlangOptions {
+        include_dirs: overlay.include_dirs(&inc_dirs),
+        // the slang defines come last to take precedence
+        defines: defines(conf)
+            .into_iter()
+            .chain(conf.slang.defines.iter().cloned())
+            .collect(),
+        warnings: conf.slang.warnings.clone(),
+        top_modules: conf.slang.top_modules.clone(),
+        language_version: conf.slang.language_version.clone(),
+    }
+}
+
+#[cfg(feature = "veridian_slang")]
+/// the sources slang compiles, open files are compiled from the editor's text and other files
+/// from disk
+fn slang_sources(
+    path: Option<&Path>,
+    rope: &Rope,
+    files: Vec<Url>,
+    open_files: &[(Url, Rope)],
+    conf: &ProjectConfig,
+) -> (Vec<SlangBuffer>, Vec<PathBuf>) {
+    let mut buffers: Vec<SlangBuffer> = Vec::new();
+    if let Some(path) = path {
+        buffers.push(SlangBuffer {
+            name: path.to_path_buf(),
+            text: rope.to_string(),
+        });
+    }
+    for (url, text) in open_files {
+        if let Ok(open_path) = url.to_file_path() {
+            if !buffers.iter().any(|x| x.name == open_path) {
+                buffers.push(SlangBuffer {
+                    name: open_path,
+                    text: text.to_string(),
+                });
+            }
+        }
+    }
+    let paths = get_paths(files, conf.auto_search_workdir, &conf.extensions)
+        .into_iter()
+        .map(|x| absolute_path(x.to_str().unwrap_or_default()))
+        .filter(|x| !buffers.iter().any(|y| &y.name == x))
+        .collect();
+    (buffers, paths)
+}
+
+#[cfg(feature = "veridian_slang")]
+/// the slang session shared by every request, so unchanged files are not reparsed
+fn slang_session() -> &'static SlangSession {
+    static SESSION: std::sync::OnceLock<SlangSession> = std::sync::OnceLock::new();
+    SESSION.get_or_init(SlangSession::new)
+}
+
+#[cfg(feature = "veridian_slang")]
+/// elaborate the design with the shared slang session, using the same sources and options as
+/// the diagnostics of the file
+pub fn slang_design(
+    uri: &Url,
+    rope: &Rope,
+    files: Vec<Url>,
+    open_files: &[(Url, Rope)],
+    conf: &ProjectConfig,
+) -> Option<SlangDesign> {
+    let path = uri.to_file_path().ok();
+    let (buffers, paths) = slang_sources(path.as_deref(), rope, files, open_files, conf);
+    let options = slang_options(path.as_deref(), &Overlay::new(), conf);
+    match slang_session().design(buffers, paths, &options) {
+        Ok(design) => Some(design),
+        Err(e) => {
+            error!("slang failed: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "veridian_slang")]
+/// convert the diagnostics slang reported for a file, errors without a file, ex. include dirs
+/// that can't be read, are reported at the start of the file
+fn parse_report(uri: Url, report: Vec<SlangDiagnostic>, overlay: &Overlay) -> Vec<Diagnostic> {
+    let path = match uri.to_file_path() {
+        Ok(path) => path,
+        Err(_) => return Vec::new(),
+    };
     let mut diagnostics: Vec<Diagnostic> = Vec::new();
-    for line in report.lines() {
-        let diag: Vec<&str> = line.splitn(5, ':').collect();
-        if absolute_path(diag.first().unwrap()) == uri.to_file_path().unwrap().as_os_str() {
-            let pos = Position::new(
-                diag.get(1).unwrap().parse::<u32>().unwrap() - 1,
-                diag.get(2).unwrap().parse::<u32>().unwrap() - 1,
-            );
-            diagnostics.push(Diagnostic::new(
-                Range::new(pos, pos),
-                slang_severity(diag.get(3).unwrap()),
-                None,
-                Some("slang".to_owned()),
-                (*diag.get(4).unwrap()).to_owned(),
-                None,
-                None,
-            ))
+    for diag in report {
+        if !diag.file.as_os_str().is_empty()
+            && overlay.original_path(&absolute_path(&diag.file.to_string_lossy())) != path
+        {
+            continue;
         }
+        let severity = match diag.severity {
+            SlangSeverity::Error | SlangSeverity::Fatal => DiagnosticSeverity::ERROR,
+            SlangSeverity::Warning => DiagnosticSeverity::WARNING,
+            SlangSeverity::Note => DiagnosticSeverity::INFORMATION,
+            SlangSeverity::Ignored => continue,
+        };
+        // prefer the warning option, it is what users pass to -W
+        let code = if diag.option.is_empty() {
+            diag.code.clone()
+        } else {
+            diag.option.clone()
+        };
+        let related: Vec<DiagnosticRelatedInformation> = diag
+            .notes
+            .iter()
+            .chain(diag.expansions.iter())
+            .filter_map(|x| {
+                let file = overlay.original_path(&absolute_path(&x.file.to_string_lossy()));
+                Some(DiagnosticRelatedInformation {
+                    location: Location::new(Url::from_file_path(file).ok()?, slang_range(x)),
+                    message: x.message.clone(),
+                })
+            })
+            .collect();
+        diagnostics.push(Diagnostic::new(
+            slang_range(&diag),
+            Some(severity),
+            (!code.is_empty()).then_some(NumberOrString::String(code)),
+            Some("slang".to_owned()),
+            diag.message,
+            (!related.is_empty()).then_some(related),
+            None,
+        ));
     }
     diagnostics
 }
 
-#[cfg(feature = "slang")]
-fn slang_severity(severity: &str) -> Option<DiagnosticSeverity> {
-    match severity {
-        " error" => Some(DiagnosticSeverity::ERROR),
-        " warning" => Some(DiagnosticSeverity::WARNING),
-        " note" => Some(DiagnosticSeverity::INFORMATION),
-        _ => None,
+/// convert 1-based slang lines and columns to a range
+#[cfg(feature = "veridian_slang")]
+fn slang_range(diag: &SlangDiagnostic) -> Range {
+    match diag.range {
+        Some(((start_line, start_col), (end_line, end_col))) => Range::new(
+            Position::new(start_line.saturating_sub(1), start_col.saturating_sub(1)),
+            Position::new(end_line.saturating_sub(1), end_col.saturating_sub(1)),
+        ),
+        None => Range::default(),
     }
 }
 
-#[cfg(feature = "slang")]
 // convert relative path to absolute
 fn absolute_path(path_str: &str) -> PathBuf {
     let path = Path::new(path_str);
@@ -213,22 +483,56 @@ fn verilator_severity(severity: &str) -> Option<DiagnosticSeverity> {
     }
 }
 
-/// syntax checking using verilator --lint-only
+/// syntax checking using verilator --lint-only, diagnostics are grouped by file path
 fn verilator_syntax(
     rope: &Rope,
     file_path: PathBuf,
+    include_dirs: &[PathBuf],
+    overlay: &Overlay,
     verilator_syntax_path: &str,
     verilator_syntax_args: &[String],
-) -> Option<Vec<Diagnostic>> {
-    let mut child = Command::new(verilator_syntax_path)
-        .stdin(Stdio::piped())
-        .stderr(Stdio::piped())
-        .stdout(Stdio::piped())
-        .args(verilator_syntax_args)
-        .arg(file_path.to_str()?)
-        .spawn()
-        .ok()?;
+    cancel: &CancelToken,
+) -> Option<HashMap<PathBuf, Vec<Diagnostic>>> {
+    // write file to stdin, read output from stderr
+    let output = run_command(
+        Command::new(verilator_syntax_path)
+            .args(verilator_syntax_args)
+            .args(include_dirs.iter().map(|x| format!("-I{}", x.display())))
+            .arg(file_path.to_str()?),
+        Some(rope),
+        cancel,
+    )?;
+    let raw_output = String::from_utf8(output.stderr).ok()?;
+    // map files staged in the overlay back to the original files
+    let mut diags: HashMap<PathBuf, Vec<Diagnostic>> = HashMap::new();
+    for (path, mut file_diags) in parse_verilator_output(&raw_output) {
+        for diag in &mut file_diags {
+            for related in diag.related_information.iter_mut().flatten() {
+                if let Ok(path) = related.location.uri.to_file_path() {
+                    if let Ok(uri) = Url::from_file_path(overlay.original_path(&path)) {
+                        related.location.uri = uri;
+                    }
+                }
+            }
+        }
+        diags
+            .entry(overlay.original_path(&path))
+            .or_default()
+            .append(&mut file_diags);
+    }
+    // make sure stale diagnostics for this file are cleared
+    diags.entry(overlay.original_path(&file_path)).or_default();
+    Some(diags)
+}
 
+/// the location a verilator caret line applies to
+enum CaretTarget {
+    Main,
+    Related(usize),
+}
+
+/// parse verilator messages along with the context lines following each message
+fn parse_verilator_output(raw_output: &str) -> HashMap<PathBuf, Vec<Diagnostic>> {
     static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
     let re = RE.get_or_init(|| {
         Regex::new(
@@ -236,54 +540,96 @@ fn verilator_syntax(
         )
         .unwrap()
     });
-    // write file to stdin, read output from stdout
-    rope.write_to(child.stdin.as_mut()?).ok()?;
-    let output = child.wait_with_output().ok()?;
-    if !output.status.success() {
-        let mut diags: Vec<Diagnostic> = Vec::new();
-        let raw_output = String::from_utf8(output.stderr).ok()?;
-        let filtered_output = raw_output
-            .lines()
-            .filter(|line| line.starts_with('%'))
-            .collect::<Vec<&str>>();
-        for error in filtered_output {
-            let caps = match re.captures(error) {
+    // secondary locations, ex. "t.sv:3:9: ... Location of original declaration"
+    static LOC_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let loc_re = LOC_RE.get_or_init(|| {
+        Regex::new(
+            r"^\s*(?P<filepath>[^\s:][^:]*):(?P<line>\d+):(?P<col>\d+): \.\.\. (?P<message>.*)$",
+        )
+        .unwrap()
+    });
+    // underline of the source context, ex. "      |   ^~~~~"
+    static CARET_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let caret_re = CARET_RE.get_or_init(|| Regex::new(r"^\s*\|\s*(?P<caret>\^~*)").unwrap());
+
+    let mut diags: Vec<(PathBuf, Diagnostic)> = Vec::new();
+    let mut caret_target: Option<CaretTarget> = None;
+    for line in raw_output.lines() {
+        if line.starts_with('%') {
+            caret_target = None;
+            let caps = match re.captures(line) {
                 Some(caps) => caps,
                 None => continue,
             };
-
-            // check if diagnostic is for this file, since verilator can provide diagnostics for
-            // included files
-            if caps.name("filepath")?.as_str() != file_path.to_str().unwrap_or("") {
-                continue;
-            }
-            let severity = verilator_severity(caps.name("severity")?.as_str());
-            let line: u32 = caps.name("line")?.as_str().to_string().parse().ok()?;
-            let col: u32 = caps.name("col").map_or("1", |m| m.as_str()).parse().ok()?;
-            let pos = Position::new(line - 1, col - 1);
-            let msg = match severity {
-                Some(DiagnosticSeverity::ERROR) => caps.name("message")?.as_str().to_string(),
-                Some(DiagnosticSeverity::WARNING) => format!(
-                    "{}: {}",
-                    caps.name("warning_type")?.as_str(),
-                    caps.name("message")?.as_str()
-                ),
-                _ => "".to_string(),
+            let pos = match verilator_pos(caps.name("line"), caps.name("col")) {
+                Some(pos) => pos,
+                None => continue,
             };
-            diags.push(Diagnostic::new(
-                Range::new(pos, pos),
-                severity,
-                None,
-                Some("verilator".to_string()),
-                msg,
-                None,
-                None,
+            diags.push((
+                absolute_path(&caps["filepath"]),
+                Diagnostic::new(
+                    Range::new(pos, pos),
+                    verilator_severity(&caps["severity"]),
+                    caps.name("warning_type")
+                        .map(|x| NumberOrString::String(x.as_str().to_string())),
+                    Some("verilator".to_string()),
+                    caps["message"].to_string(),
+                    None,
+                    None,
+                ),
             ));
+            caret_target = Some(CaretTarget::Main);
+        } else if let Some(caps) = loc_re.captures(line) {
+            let (_, diag) = match diags.last_mut() {
+                Some(diag) if caret_target.is_some() => diag,
+                _ => continue,
+            };
+            let pos = match verilator_pos(caps.name("line"), caps.name("col")) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let uri = match Url::from_file_path(absolute_path(&caps["filepath"])) {
+                Ok(uri) => uri,
+                Err(_) => continue,
+            };
+            let related = diag.related_information.get_or_insert_with(Vec::new);
+            related.push(DiagnosticRelatedInformation {
+                location: Location::new(uri, Range::new(pos, pos)),
+                message: caps["message"].to_string(),
+            });
+            caret_target = Some(CaretTarget::Related(related.len() - 1));
+        } else if let Some(caps) = caret_re.captures(line) {
+            let (_, diag) = match diags.last_mut() {
+                Some(diag) => diag,
+                None => continue,
+            };
+            // only the first caret line after a location applies to it
+            let range = match caret_target.take() {
+                Some(CaretTarget::Main) => &mut diag.range,
+                Some(CaretTarget::Related(i)) => {
+                    match diag.related_information.as_mut().and_then(|x| x.get_mut(i)) {
+                        Some(related) => &mut related.location.range,
+                        None => continue,
+                    }
+                }
+                None => continue,
+            };
+            range.end.character = range.start.character + caps["caret"].chars().count() as u32;
         }
-        Some(diags)
-    } else {
-        None
     }
+
+    let mut result: HashMap<PathBuf, Vec<Diagnostic>> = HashMap::new();
+    for (path, diag) in diags {
+        result.entry(path).or_default().push(diag);
+    }
+    result
+}
+
+/// convert a 1-indexed verilator line and optional column to a position
+fn verilator_pos(line: Option<regex::Match>, col: Option<regex::Match>) -> Option<Position> {
+    let line: u32 = line?.as_str().parse().ok()?;
+    let col: u32 = col.map_or("1", |m| m.as_str()).parse().ok()?;
+    Some(Position::new(line.checked_sub(1)?, col.saturating_sub(1)))
 }
 
 /// syntax checking using verible-verilog-syntax
@@ -291,16 +637,8 @@ fn verible_syntax(
     rope: &Rope,
     verible_syntax_path: &str,
     verible_syntax_args: &[String],
+    cancel: &CancelToken,
 ) -> Option<Vec<Diagnostic>> {
-    let mut child = Command::new(verible_syntax_path)
-        .stdin(Stdio::piped())
-        .stderr(Stdio::piped())
-        .stdout(Stdio::piped())
-        .args(verible_syntax_args)
-        .arg("-")
-        .spawn()
-        .ok()?;
-
     static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
     let re = RE.get_or_init(|| {
         Regex::new(
@@ -309,8 +647,13 @@ fn verible_syntax(
         .unwrap()
     });
     // write file to stdin, read output from stdout
-    rope.write_to(child.stdin.as_mut()?).ok()?;
-    let output = child.wait_with_output().ok()?;
+    let output = run_command(
+        Command::new(verible_syntax_path)
+            .args(verible_syntax_args)
+            .arg("-"),
+        Some(rope),
+        cancel,
+    )?;
     if !output.status.success() {
         let mut diags: Vec<Diagnostic> = Vec::new();
         let raw_output = String::from_utf8(output.stdout).ok()?;
@@ -350,32 +693,55 @@ mod tests {
     use tempdir::TempDir;
 
     #[test]
-    #[cfg(feature = "slang")]
+    #[cfg(feature = "veridian_slang")]
     fn test_diagnostics() {
         test_init();
         let uri = Url::from_file_path(absolute_path("test_data/diag/diag_test.sv")).unwrap();
-        let expected = PublishDiagnosticsParams::new(
-            uri.clone(),
-            vec![Diagnostic::new(
-                Range::new(Position::new(3, 13), Position::new(3, 13)),
-                Some(DiagnosticSeverity::WARNING),
-                None,
-                Some("slang".to_owned()),
-                " cannot refer to element 2 of \'logic[1:0]\' [-Windex-oob]".to_owned(),
-                None,
-                None,
-            )],
-            None,
-        );
         let diag = get_diagnostics(
             uri.clone(),
             &Rope::default(),
-            vec![uri],
+            vec![uri.clone()],
+            Vec::new(),
+            Vec::new(),
             &ProjectConfig::default(),
+            &CancelToken::default(),
+        )
+        .remove(0);
+        assert_eq!(diag.uri, uri);
+        assert_eq!(diag.version, None);
+        let diag = diag.diagnostics.last().unwrap();
+        assert_eq!(diag.range.start, Position::new(3, 13));
+        assert_eq!(diag.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(
+            diag.code,
+            Some(NumberOrString::String("index-oob".to_owned()))
         );
-        assert_eq!(diag.uri, expected.uri);
-        assert_eq!(diag.version, expected.version);
-        assert_eq!(diag.diagnostics.last(), expected.diagnostics.last());
+        assert_eq!(diag.source, Some("slang".to_owned()));
+        assert_eq!(diag.message, "cannot refer to element 2 of \'logic[1:0]\'");
+    }
+
+    #[test]
+    #[cfg(feature = "veridian_slang")]
+    fn test_slang_errors_without_file() {
+        let uri = Url::from_file_path(absolute_path("test_data/diag/diag_test.sv")).unwrap();
+        let error = |file: &str| SlangDiagnostic {
+            file: PathBuf::from(file),
+            range: None,
+            severity: SlangSeverity::Error,
+            code: String::new(),
+            option: String::new(),
+            message: "include dir 'inc': No such file or directory".to_owned(),
+            notes: Vec::new(),
+            expansions: Vec::new(),
+        };
+        let diagnostics = parse_report(
+            uri,
+            vec![error(""), error("test_data/other.sv")],
+            &Overlay::new(),
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range, Range::default());
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
     }
 
     #[test]
@@ -386,7 +752,33 @@ mod tests {
             uri.clone(),
             &Rope::default(),
             vec![uri],
+            Vec::new(),
+            Vec::new(),
             &ProjectConfig::default(),
+            &CancelToken::default(),
+        );
+    }
+
+    #[test]
+    fn test_linter_include_dirs() {
+        let dir = TempDir::new("linter_includes").unwrap();
+        let header = dir.path().join("defs.svh");
+        fs::write(&header, "`define WIDTH 8\n").unwrap();
+        let conf = ProjectConfig {
+            include_dirs: vec![dir.path().display().to_string()],
+            ..ProjectConfig::default()
+        };
+        let mut overlay = Overlay::new();
+        let saved = vec![dir.path().display().to_string()];
+        assert_eq!(linter_include_dirs(&overlay, &conf), saved);
+        // an unsaved header is searched for in the overlay first
+        overlay.stage(&header, &Rope::from_str("`define WIDTH 16\n"));
+        assert_eq!(
+            linter_include_dirs(&overlay, &conf),
+            vec![
+                overlay.mirror(dir.path()).unwrap().display().to_string(),
+                dir.path().display().to_string()
+            ]
         );
     }
 
@@ -400,7 +792,7 @@ mod tests {
 endmodule
 "#;
         let doc = Rope::from_str(text);
-        let errors = verible_syntax(&doc, "verible-verilog-syntax", &[])
+        let errors = verible_syntax(&doc, "verible-verilog-syntax", &[], &CancelToken::default())
             .expect("verible-verilog-syntax not found, test can not run");
         let expected: Vec<Diagnostic> = vec![Diagnostic {
             range: Range {
@@ -446,15 +838,19 @@ endmodule
 
         let errors = verilator_syntax(
             &doc,
-            file_path_1,
+            file_path_1.clone(),
+            &[],
+            &Overlay::new(),
             "verilator",
             &[
                 "--lint-only".to_string(),
                 "--sv".to_string(),
                 "-Wall".to_string(),
             ],
+            &CancelToken::default(),
         )
         .expect("verilator not found, test can not run");
+        let errors = &errors[&file_path_1];
 
         drop(f);
         dir.close().unwrap();
@@ -485,4 +881,61 @@ endmodule
         assert_eq!(errors[0].range.end.line, expected[0].range.end.line);
         assert!(errors[0].message.contains("syntax error"));
     }
+
+    #[test]
+    fn test_parse_verilator_output() {
+        let output = r#"%Warning-WIDTH: /src/test.sv:5:14: Operator ASSIGNW expects 1 bits on the Assign RHS, but Assign RHS's CONST '2'h3' generates 2 bits.
+                                : ... In instance test
+    5 |   assign a = 2'b11;
+      |              ^~~~~
+                ... For warning description see https://verilator.org/warn/WIDTH?v=5.020
+%Warning-MULTIDRIVEN: /src/test.sv:3:9: Signal has multiple driving blocks with different clocking: 'b'
+                      /src/inc.svh:7:5: ... Location of other driving block
+    7 |     b <= 1'b1;
+      |     ^
+%Error: /src/inc.svh:2:1: syntax error, unexpected endmodule
+    2 | endmodule
+      | ^~~~~~~~~
+%Error: Exiting due to 1 error(s)
+"#;
+        let diags = parse_verilator_output(output);
+        let test_sv = absolute_path("/src/test.sv");
+        let inc_svh = absolute_path("/src/inc.svh");
+        assert_eq!(diags.len(), 2);
+
+        let test_diags = &diags[&test_sv];
+        assert_eq!(test_diags.len(), 2);
+        assert_eq!(
+            test_diags[0].range,
+            Range::new(Position::new(4, 13), Position::new(4, 18))
+        );
+        assert!(test_diags[0].message.starts_with("Operator ASSIGNW"));
+        assert_eq!(
+            test_diags[0].code,
+            Some(NumberOrString::String("WIDTH".to_string()))
+        );
+        assert_eq!(
+            test_diags[1].range,
+            Range::new(Position::new(2, 8), Position::new(2, 8))
+        );
+        let related = test_diags[1].related_information.as_ref().unwrap();
+        assert_eq!(
+            related,
+            &vec![DiagnosticRelatedInformation {
+                location: Location::new(
+                    Url::from_file_path(&inc_svh).unwrap(),
+                    Range::new(Position::new(6, 4), Position::new(6, 5))
+                ),
+                message: "Location of other driving block".to_string(),
+            }]
+        );
+
+        let inc_diags = &diags[&inc_svh];
+        assert_eq!(inc_diags.len(), 1);
+        assert_eq!(inc_diags[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(
+            inc_diags[0].range,
+            Range::new(Position::new(1, 0), Position::new(1, 9))
+        );
+    }
 }
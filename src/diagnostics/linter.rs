@@ -0,0 +1,207 @@
+use super::absolute_path;
+use super::process::{run_command, CancelToken};
+use crate::server::{Linter, LinterSeverity};
+use log::{debug, error};
+use regex::Regex;
+use ropey::Rope;
+use std::path::Path;
+use std::process::Command;
+use tower_lsp::lsp_types::*;
+
+/// run a user defined linter from the project config
+pub fn custom_linter(
+    rope: &Rope,
+    file_path: &Path,
+    include_dirs: &[String],
+    defines: &[String],
+    linter: &Linter,
+    cancel: &CancelToken,
+) -> Option<Vec<Diagnostic>> {
+    let re = match Regex::new(&linter.regex) {
+        Ok(re) => re,
+        Err(e) => {
+            error!("invalid regex for linter {}: {}", linter.name, e);
+            return None;
+        }
+    };
+    let args = expand_args(&linter.args, file_path.to_str()?, include_dirs, defines);
+    debug!("running linter {}: {} {:?}", linter.name, linter.path, args);
+    // write file to stdin, read output from stdout and stderr
+    let output = run_command(
+        Command::new(&linter.path).args(args),
+        if linter.stdin { Some(rope) } else { None },
+        cancel,
+    )?;
+    let mut raw_output = String::from_utf8(output.stdout).ok()?;
+    raw_output.push_str(&String::from_utf8(output.stderr).ok()?);
+    Some(parse_linter_output(
+        &raw_output,
+        &re,
+        file_path,
+        &linter.name,
+        linter.severity,
+    ))
+}
+
+/// substitute ${file}, ${includes} and ${defines} in the linter arguments
+fn expand_args(
+    args: &[String],
+    file_path: &str,
+    include_dirs: &[String],
+    defines: &[String],
+) -> Vec<String> {
+    let mut expanded: Vec<String> = Vec::new();
+    for arg in args {
+        let arg = arg.replace("${file}", file_path);
+        if arg.contains("${includes}") {
+            for dir in include_dirs {
+                expanded.push(arg.replace("${includes}", dir));
+            }
+        } else if arg.contains("${defines}") {
+            for define in defines {
+                expanded.push(arg.replace("${defines}", define));
+            }
+        } else {
+            expanded.push(arg);
+        }
+    }
+    expanded
+}
+
+/// parse linter output, keeping only the diagnostics for the given file
+fn parse_linter_output(
+    output: &str,
+    re: &Regex,
+    file_path: &Path,
+    source: &str,
+    default_severity: LinterSeverity,
+) -> Vec<Diagnostic> {
+    let mut diags: Vec<Diagnostic> = Vec::new();
+    for text in output.lines() {
+        let caps = match re.captures(text) {
+            Some(caps) => caps,
+            None => continue,
+        };
+        // linters reading from stdin usually report the file as "-" or "<stdin>"
+        if let Some(file) = caps.name("file") {
+            let file = file.as_str();
+            if file != "-" && file != "<stdin>" && absolute_path(file) != file_path {
+                continue;
+            }
+        }
+        let line: u32 = match caps.name("line").map(|x| x.as_str().parse()) {
+            Some(Ok(line)) if line > 0 => line,
+            _ => continue,
+        };
+        let col: u32 = caps
+            .name("col")
+            .and_then(|x| x.as_str().parse().ok())
+            .unwrap_or(1)
+            .max(1);
+        let pos = Position::new(line - 1, col - 1);
+        let severity = caps
+            .name("severity")
+            .and_then(|x| linter_severity(x.as_str()))
+            .unwrap_or_else(|| default_severity.into());
+        let code = caps
+            .name("code")
+            .map(|x| NumberOrString::String(x.as_str().to_string()));
+        let message = caps
+            .name("message")
+            .map_or(text.trim().to_string(), |x| x.as_str().trim().to_string());
+        diags.push(Diagnostic::new(
+            Range::new(pos, pos),
+            Some(severity),
+            code,
+            Some(source.to_string()),
+            message,
+            None,
+            None,
+        ));
+    }
+    diags
+}
+
+/// convert captured severity string to DiagnosticSeverity
+fn linter_severity(severity: &str) -> Option<DiagnosticSeverity> {
+    let severity = severity.to_lowercase();
+    if severity.starts_with("err") || severity.starts_with("fatal") {
+        Some(DiagnosticSeverity::ERROR)
+    } else if severity.starts_with("warn") {
+        Some(DiagnosticSeverity::WARNING)
+    } else if severity.starts_with("info") || severity.starts_with("note") {
+        Some(DiagnosticSeverity::INFORMATION)
+    } else if severity.starts_with("hint") || severity.starts_with("style") {
+        Some(DiagnosticSeverity::HINT)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_args() {
+        let args = vec![
+            "--lint".to_string(),
+            "-I${includes}".to_string(),
+            "-D${defines}".to_string(),
+            "${file}".to_string(),
+        ];
+        let includes = vec!["inc1".to_string(), "inc2".to_string()];
+        let defines = vec!["SIMULATION".to_string(), "WIDTH=8".to_string()];
+        assert_eq!(
+            expand_args(&args, "test.sv", &includes, &defines),
+            vec![
+                "--lint",
+                "-Iinc1",
+                "-Iinc2",
+                "-DSIMULATION",
+                "-DWIDTH=8",
+                "test.sv"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_linter_output() {
+        let output = r#"test.sv:3:5: warning [W100] unused signal 'a'
+other.sv:1:1: error [E1] syntax error
+test.sv:6: error [E2] syntax error
+"#;
+        let re = Regex::new(
+            r"^(?P<file>[^:]+):(?P<line>\d+):((?P<col>\d+):)? (?P<severity>\w+) \[(?P<code>\w+)\] (?P<message>.*)$",
+        )
+        .unwrap();
+        let diags = parse_linter_output(
+            output,
+            &re,
+            &absolute_path("test.sv"),
+            "lint",
+            LinterSeverity::Warning,
+        );
+        let expected = vec![
+            Diagnostic::new(
+                Range::new(Position::new(2, 4), Position::new(2, 4)),
+                Some(DiagnosticSeverity::WARNING),
+                Some(NumberOrString::String("W100".to_string())),
+                Some("lint".to_string()),
+                "unused signal 'a'".to_string(),
+                None,
+                None,
+            ),
+            Diagnostic::new(
+                Range::new(Position::new(5, 0), Position::new(5, 0)),
+                Some(DiagnosticSeverity::ERROR),
+                Some(NumberOrString::String("E2".to_string())),
+                Some("lint".to_string()),
+                "syntax error".to_string(),
+                None,
+                None,
+            ),
+        ];
+        assert_eq!(diags, expected);
+    }
+}
@@ -0,0 +1,176 @@
+use log::{debug, error};
+use ropey::Rope;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tempfile::TempDir;
+
+/// A private temporary directory mirroring the absolute paths of unsaved buffers, so that
+/// external tools see the editor's text instead of the saved file. The directory has a random
+/// name, is only accessible by the user, and is removed when the overlay is dropped.
+pub struct Overlay {
+    // created when the first buffer is staged
+    root: Option<TempDir>,
+    staged: Vec<PathBuf>,
+}
+
+impl Overlay {
+    pub fn new() -> Self {
+        Self {
+            root: None,
+            staged: Vec::new(),
+        }
+    }
+
+    /// write a buffer into the overlay if it differs from the file on disk, returning the path
+    /// tools should be given for this file
+    pub fn stage(&mut self, path: &Path, text: &Rope) -> PathBuf {
+        if let Ok(saved) = fs::read_to_string(path) {
+            if *text == saved.as_str() {
+                return path.to_path_buf();
+            }
+        }
+        if self.root.is_none() {
+            match private_dir() {
+                Ok(root) => self.root = Some(root),
+                Err(e) => {
+                    error!("failed to create the overlay directory: {}", e);
+                    return path.to_path_buf();
+                }
+            }
+        }
+        let Some(staged) = self.mirror(path) else {
+            return path.to_path_buf();
+        };
+        if let Err(e) = staged
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&staged, text.to_string()))
+        {
+            error!("failed to stage {:?}: {}", path, e);
+            return path.to_path_buf();
+        }
+        debug!("staged {:?} at {:?}", path, staged);
+        self.staged.push(path.to_path_buf());
+        staged
+    }
+
+    /// whether a file was staged into the overlay
+    #[cfg(test)]
+    pub fn is_staged(&self, path: &Path) -> bool {
+        self.staged.iter().any(|x| x == path)
+    }
+
+    /// the location of a path inside the overlay, None before a buffer is staged
+    pub fn mirror(&self, path: &Path) -> Option<PathBuf> {
+        let mut mirror = self.root.as_ref()?.path().to_path_buf();
+        for component in path.components() {
+            match component {
+                Component::Normal(x) => mirror.push(x),
+                Component::ParentDir => {
+                    mirror.pop();
+                }
+                Component::Prefix(x) => {
+                    mirror.push(x.as_os_str().to_string_lossy().replace(':', ""));
+                }
+                Component::RootDir | Component::CurDir => (),
+            }
+        }
+        Some(mirror)
+    }
+
+    /// include directories for tools, the overlay copy of a directory is searched first if any
+    /// file in it was staged
+    pub fn include_dirs(&self, dirs: &[PathBuf]) -> Vec<PathBuf> {
+        let mut result: Vec<PathBuf> = Vec::new();
+        for dir in dirs {
+            if self.staged.iter().any(|x| x.starts_with(dir)) {
+                result.extend(self.mirror(dir));
+            }
+            result.push(dir.clone());
+        }
+        result
+    }
+
+    /// map a path reported by a tool back to the original file, paths that aren't staged are
+    /// returned unchanged
+    pub fn original_path(&self, path: &Path) -> PathBuf {
+        self.staged
+            .iter()
+            .find(|x| self.mirror(x).as_deref() == Some(path))
+            .cloned()
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+}
+
+/// a temporary directory with a random name, readable only by the user before anything is
+/// written to it
+fn private_dir() -> std::io::Result<TempDir> {
+    let dir = tempfile::Builder::new().prefix("veridian-").tempdir()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_overlay() {
+        let dir = TempDir::new("overlay_test").unwrap();
+        let saved_path = dir.path().join("saved.sv");
+        let unsaved_path = dir.path().join("unsaved.sv");
+        for path in [&saved_path, &unsaved_path] {
+            let mut f = File::create(path).unwrap();
+            f.write_all(b"module test; endmodule\n").unwrap();
+            f.sync_all().unwrap();
+        }
+
+        let mut overlay = Overlay::new();
+        let saved = overlay.stage(&saved_path, &Rope::from_str("module test; endmodule\n"));
+        assert_eq!(saved, saved_path);
+        assert!(!overlay.is_staged(&saved_path));
+        // the directory is only created for the first unsaved buffer
+        assert_eq!(overlay.mirror(&saved_path), None);
+
+        let text = "module test; logic a; endmodule\n";
+        let staged = overlay.stage(&unsaved_path, &Rope::from_str(text));
+        assert_ne!(staged, unsaved_path);
+        assert!(overlay.is_staged(&unsaved_path));
+        assert_eq!(fs::read_to_string(&staged).unwrap(), text);
+        assert_eq!(overlay.original_path(&staged), unsaved_path);
+        assert_eq!(overlay.original_path(&saved_path), saved_path);
+        // a path in the overlay that wasn't staged isn't mapped to a made up file
+        let other = overlay.mirror(&saved_path).unwrap();
+        assert_eq!(overlay.original_path(&other), other);
+        assert_eq!(
+            overlay.include_dirs(&[dir.path().to_path_buf()]),
+            vec![
+                overlay.mirror(dir.path()).unwrap(),
+                dir.path().to_path_buf()
+            ]
+        );
+        let root = overlay.root.as_ref().unwrap().path().to_path_buf();
+        assert!(root
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("veridian-"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&root).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+
+        drop(overlay);
+        assert!(!staged.exists());
+        assert!(!root.exists());
+    }
+}
@@ -0,0 +1,109 @@
+use ropey::Rope;
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Cancels in-flight external tools, ex. when a newer version of a document arrives
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// run a command to completion, writing the given text to stdin. If the token is cancelled the
+/// process is killed and None is returned.
+pub fn run_command(
+    command: &mut Command,
+    input: Option<&Rope>,
+    cancel: &CancelToken,
+) -> Option<Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .ok()?;
+
+    // write to stdin and read from stdout/stderr on separate threads so the pipes can't fill up
+    let stdin = child.stdin.take();
+    let input = input.cloned();
+    let writer = thread::spawn(move || {
+        if let (Some(mut stdin), Some(input)) = (stdin, input) {
+            let _ = input.write_to(&mut stdin);
+        }
+    });
+    let mut stdout = child.stdout.take()?;
+    let stdout_reader = thread::spawn(move || {
+        let mut buf: Vec<u8> = Vec::new();
+        let _ = stdout.read_to_end(&mut buf);
+        buf
+    });
+    let mut stderr = child.stderr.take()?;
+    let stderr_reader = thread::spawn(move || {
+        let mut buf: Vec<u8> = Vec::new();
+        let _ = stderr.read_to_end(&mut buf);
+        buf
+    });
+
+    let status = loop {
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => thread::sleep(Duration::from_millis(5)),
+            Err(_) => return None,
+        }
+    };
+    let _ = writer.join();
+    Some(Output {
+        status,
+        stdout: stdout_reader.join().ok()?,
+        stderr: stderr_reader.join().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_run_command() {
+        let output = run_command(
+            &mut Command::new("cat"),
+            Some(&Rope::from_str("module test; endmodule")),
+            &CancelToken::default(),
+        )
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"module test; endmodule");
+    }
+
+    #[test]
+    fn test_cancel_command() {
+        let cancel = CancelToken::default();
+        let cancel2 = cancel.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel2.cancel();
+        });
+        let now = Instant::now();
+        let output = run_command(Command::new("sleep").arg("10"), None, &cancel);
+        handle.join().unwrap();
+        assert!(output.is_none());
+        assert!(now.elapsed() < Duration::from_secs(5));
+    }
+}
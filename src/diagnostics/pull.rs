@@ -0,0 +1,452 @@
+use super::{filter_diagnostics, get_diagnostics, CancelToken};
+use crate::server::LSPServer;
+use log::debug;
+use ropey::Rope;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use tower_lsp::lsp_types::*;
+
+/// the last diagnostics computed for a file on a pull request
+#[derive(Clone)]
+pub struct PulledReport {
+    // a hash of the text the report was computed for
+    text_hash: u64,
+    // diagnostics of the linters, they are only run again when the text of the file changes
+    lint: Vec<Diagnostic>,
+    // diagnostics of the semantic checks, they are run again when the text of the file or the
+    // declarations they depend on change
+    semantic: Vec<Diagnostic>,
+    // the names the semantic checks looked up in other files and the signature of their
+    // declarations, None if the checks ran while files were being indexed
+    dependencies: Option<(Vec<String>, u64)>,
+    result_id: String,
+}
+
+impl PulledReport {
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.semantic.iter().chain(&self.lint).cloned().collect()
+    }
+}
+
+impl LSPServer {
+    pub fn document_diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> DocumentDiagnosticReportResult {
+        let uri = params.text_document.uri;
+        debug!("document_diagnostic: {}", &uri);
+        let mut related: HashMap<Url, DocumentDiagnosticReportKind> = HashMap::new();
+        let (result_id, items) = match self.pull_text(&uri) {
+            Some((text, _)) => {
+                let (report, others) = self.pull(&uri, &text);
+                for params in others {
+                    related.insert(
+                        params.uri,
+                        DocumentDiagnosticReportKind::Full(FullDocumentDiagnosticReport {
+                            result_id: None,
+                            items: params.diagnostics,
+                        }),
+                    );
+                }
+                (report.result_id.clone(), report.diagnostics())
+            }
+            None => (result_id(&[]), Vec::new()),
+        };
+        let related_documents = if related.is_empty() {
+            None
+        } else {
+            Some(related)
+        };
+        if params.previous_result_id.as_ref() == Some(&result_id) {
+            return DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Unchanged(
+                RelatedUnchangedDocumentDiagnosticReport {
+                    related_documents,
+                    unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                        result_id,
+                    },
+                },
+            ));
+        }
+        DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(
+            RelatedFullDocumentDiagnosticReport {
+                related_documents,
+                full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                    result_id: Some(result_id),
+                    items,
+                },
+            },
+        ))
+    }
+
+    pub fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> WorkspaceDiagnosticReportResult {
+        debug!("workspace_diagnostic");
+        let previous: HashMap<Url, String> = params
+            .previous_result_ids
+            .into_iter()
+            .map(|x| (x.uri, x.value))
+            .collect();
+        let mut uris: Vec<Url> = self.srcs.names.read().unwrap().keys().cloned().collect();
+        uris.sort();
+        let mut items: Vec<WorkspaceDocumentDiagnosticReport> = Vec::new();
+        for uri in uris {
+            let (text, version) = match self.pull_text(&uri) {
+                Some(x) => x,
+                None => continue,
+            };
+            let report = self.pull(&uri, &text).0;
+            let result_id = report.result_id.clone();
+            if previous.get(&uri) == Some(&result_id) {
+                items.push(WorkspaceDocumentDiagnosticReport::Unchanged(
+                    WorkspaceUnchangedDocumentDiagnosticReport {
+                        uri,
+                        version,
+                        unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                            result_id,
+                        },
+                    },
+                ));
+            } else {
+                items.push(WorkspaceDocumentDiagnosticReport::Full(
+                    WorkspaceFullDocumentDiagnosticReport {
+                        uri,
+                        version,
+                        full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                            result_id: Some(result_id),
+                            items: report.diagnostics(),
+                        },
+                    },
+                ));
+            }
+        }
+        WorkspaceDiagnosticReportResult::Report(WorkspaceDiagnosticReport { items })
+    }
+
+    /// the current text of a file, and its version if it is open in the editor
+    fn pull_text(&self, uri: &Url) -> Option<(Rope, Option<i64>)> {
+        let file_id = *self.srcs.names.read().unwrap().get(uri)?;
+        let file = self.srcs.get_file(file_id)?;
+        let file = file.read().unwrap();
+        let version = if file.version == -1 {
+            None
+        } else {
+            Some(file.version as i64)
+        };
+        Some((file.text.clone(), version))
+    }
+
+    /// the diagnostics of a file, and of the other files the linters reported on. The linters
+    /// only run when the text of the file changed since the last pull, and the semantic checks
+    /// when the text or the declarations they depend on changed
+    fn pull(&self, uri: &Url, text: &Rope) -> (PulledReport, Vec<PublishDiagnosticsParams>) {
+        let text_hash = text_hash(text);
+        let cached = self
+            .pulled
+            .lock()
+            .unwrap()
+            .get(uri)
+            .filter(|x| x.text_hash == text_hash)
+            .cloned();
+        let current = cached.as_ref().filter(|x| {
+            x.dependencies.as_ref().is_some_and(|(names, signature)| {
+                self.dependency_signature(names) == Some(*signature)
+            })
+        });
+        if let Some(report) = current {
+            return (report.clone(), Vec::new());
+        }
+        // before the config is locked, the semantic checks read it too
+        let semantic = self.semantic_report(uri);
+        let dependencies = semantic.dependencies.and_then(|names| {
+            let signature = self.dependency_signature(&names)?;
+            Some((names, signature))
+        });
+        let conf = self.conf.read().unwrap();
+        let (lint, others) = match cached {
+            Some(report) => (report.lint, Vec::new()),
+            None => {
+                let urls = self.srcs.names.read().unwrap().keys().cloned().collect();
+                let open_files = self.srcs.open_buffers();
+                let mut reports = get_diagnostics(
+                    uri.clone(),
+                    text,
+                    urls,
+                    open_files,
+                    Vec::new(),
+                    &conf,
+                    &CancelToken::default(),
+                )
+                .into_iter();
+                let lint = reports.next().map(|x| x.diagnostics).unwrap_or_default();
+                (lint, reports.collect())
+            }
+        };
+        let mut report = PulledReport {
+            text_hash,
+            lint,
+            semantic: filter_diagnostics(uri, Some(text), semantic.diagnostics, &conf),
+            dependencies,
+            result_id: String::new(),
+        };
+        drop(conf);
+        report.result_id = result_id(&report.diagnostics());
+        self.pulled
+            .lock()
+            .unwrap()
+            .insert(uri.clone(), report.clone());
+        (report, others)
+    }
+}
+
+/// a hash of the text of a file
+fn text_hash(text: &Rope) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    // chunk boundaries depend on the edits, so only the bytes are hashed
+    for chunk in text.chunks() {
+        hasher.write(chunk.as_bytes());
+    }
+    hasher.finish()
+}
+
+/// result ids identify a set of diagnostics, so an unchanged report can be sent when relinting
+/// gives the same result
+fn result_id(diagnostics: &[Diagnostic]) -> String {
+    let mut hasher = DefaultHasher::new();
+    format!("{:?}", diagnostics).hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+
+    fn open(server: &LSPServer, uri: &Url, text: &str) {
+        server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri.clone(),
+                "systemverilog".to_owned(),
+                1,
+                text.to_owned(),
+            ),
+        });
+    }
+
+    #[test]
+    fn test_document_diagnostic() {
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///test.sv").unwrap();
+        open(&server, &uri, "module test; endmodule\n");
+        let params = |previous_result_id| DocumentDiagnosticParams {
+            text_document: TextDocumentIdentifier::new(uri.clone()),
+            identifier: None,
+            previous_result_id,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let result_id = match server.document_diagnostic(params(None)) {
+            DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(report)) => {
+                assert!(report.full_document_diagnostic_report.items.is_empty());
+                report.full_document_diagnostic_report.result_id.unwrap()
+            }
+            report => panic!("expected full report, got {:?}", report),
+        };
+        assert!(matches!(
+            server.document_diagnostic(params(Some(result_id))),
+            DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Unchanged(_))
+        ));
+        assert!(matches!(
+            server.document_diagnostic(params(Some("stale".to_owned()))),
+            DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(_))
+        ));
+    }
+
+    #[test]
+    fn test_workspace_diagnostic() {
+        let server = LSPServer::new(None);
+        let uri1 = Url::parse("file:///test1.sv").unwrap();
+        let uri2 = Url::parse("file:///test2.sv").unwrap();
+        open(&server, &uri1, "module test1; endmodule\n");
+        open(&server, &uri2, "module test2; endmodule\n");
+        let params = |previous_result_ids| WorkspaceDiagnosticParams {
+            identifier: None,
+            previous_result_ids,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let items = match server.workspace_diagnostic(params(Vec::new())) {
+            WorkspaceDiagnosticReportResult::Report(report) => report.items,
+            report => panic!("expected report, got {:?}", report),
+        };
+        assert_eq!(items.len(), 2);
+        let mut previous: Vec<PreviousResultId> = Vec::new();
+        for item in items {
+            match item {
+                WorkspaceDocumentDiagnosticReport::Full(report) => {
+                    assert_eq!(report.version, Some(1));
+                    previous.push(PreviousResultId {
+                        uri: report.uri,
+                        value: report.full_document_diagnostic_report.result_id.unwrap(),
+                    });
+                }
+                item => panic!("expected full report, got {:?}", item),
+            }
+        }
+        previous.retain(|x| x.uri == uri1);
+        let items = match server.workspace_diagnostic(params(previous)) {
+            WorkspaceDiagnosticReportResult::Report(report) => report.items,
+            report => panic!("expected report, got {:?}", report),
+        };
+        for item in items {
+            match item {
+                WorkspaceDocumentDiagnosticReport::Unchanged(report) => {
+                    assert_eq!(report.uri, uri1)
+                }
+                WorkspaceDocumentDiagnosticReport::Full(report) => assert_eq!(report.uri, uri2),
+            }
+        }
+    }
+
+    #[test]
+    fn test_workspace_diagnostic_other_file_changed() {
+        let server = LSPServer::new(None);
+        let uri1 = Url::parse("file:///top.sv").unwrap();
+        let uri2 = Url::parse("file:///sub.sv").unwrap();
+        open(&server, &uri1, "module top; sub u_sub(); endmodule\n");
+        let pull = || match server.workspace_diagnostic(WorkspaceDiagnosticParams {
+            identifier: None,
+            previous_result_ids: Vec::new(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        }) {
+            WorkspaceDiagnosticReportResult::Report(report) => report.items,
+            report => panic!("expected report, got {:?}", report),
+        };
+        let items = |uri: &Url| {
+            pull()
+                .into_iter()
+                .find_map(|item| match item {
+                    WorkspaceDocumentDiagnosticReport::Full(report) if &report.uri == uri => {
+                        Some(report.full_document_diagnostic_report.items)
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert!(!items(&uri1).is_empty());
+        // declaring the module in another file relints the instantiating file
+        open(&server, &uri2, "module sub; endmodule\n");
+        assert!(items(&uri1).is_empty());
+    }
+
+    #[test]
+    fn test_workspace_diagnostic_cached() {
+        let server = LSPServer::new(None);
+        let top = Url::parse("file:///top.sv").unwrap();
+        let sub = Url::parse("file:///sub.sv").unwrap();
+        let other = Url::parse("file:///other.sv").unwrap();
+        open(&server, &top, "module top; sub u_sub(); endmodule\n");
+        open(&server, &other, "module other; endmodule\n");
+        server.srcs.wait_indexed();
+        // the result ids of the last reports
+        let previous = || -> Vec<PreviousResultId> {
+            server
+                .pulled
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(uri, report)| PreviousResultId {
+                    uri: uri.clone(),
+                    value: report.result_id.clone(),
+                })
+                .collect()
+        };
+        let pull = |previous| -> HashMap<Url, WorkspaceDocumentDiagnosticReport> {
+            match server.workspace_diagnostic(WorkspaceDiagnosticParams {
+                identifier: None,
+                previous_result_ids: previous,
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            }) {
+                WorkspaceDiagnosticReportResult::Report(report) => report
+                    .items
+                    .into_iter()
+                    .map(|item| match &item {
+                        WorkspaceDocumentDiagnosticReport::Full(x) => (x.uri.clone(), item),
+                        WorkspaceDocumentDiagnosticReport::Unchanged(x) => (x.uri.clone(), item),
+                    })
+                    .collect(),
+                report => panic!("expected report, got {:?}", report),
+            }
+        };
+        let full = |item: &WorkspaceDocumentDiagnosticReport| match item {
+            WorkspaceDocumentDiagnosticReport::Full(x) => {
+                Some(x.full_document_diagnostic_report.items.clone())
+            }
+            WorkspaceDocumentDiagnosticReport::Unchanged(_) => None,
+        };
+        let items = pull(Vec::new());
+        assert_eq!(full(&items[&top]).unwrap().len(), 1);
+        // stand in for linter findings, to tell whether the linters run again
+        let lint = Diagnostic {
+            message: "lint".to_owned(),
+            ..Default::default()
+        };
+        for report in server.pulled.lock().unwrap().values_mut() {
+            report.lint = vec![lint.clone()];
+            report.result_id = result_id(&report.diagnostics());
+        }
+        // nothing changed, so nothing is checked again
+        let items = pull(Vec::new());
+        assert!(full(&items[&top]).unwrap().contains(&lint));
+        assert!(full(&items[&other]).unwrap().contains(&lint));
+        let items = pull(previous());
+        assert!(full(&items[&top]).is_none());
+        assert!(full(&items[&other]).is_none());
+        // declaring a unit used by top only runs the semantic checks of top again
+        open(&server, &sub, "module sub; endmodule\n");
+        server.srcs.wait_indexed();
+        let items = pull(previous());
+        assert_eq!(full(&items[&top]).unwrap(), vec![lint.clone()]);
+        assert!(full(&items[&other]).is_none());
+        assert!(full(&items[&sub]).unwrap().is_empty());
+        // changing the text of a file lints it again
+        server.did_change(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(other.clone(), 2),
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "module other; logic a; endmodule\n".to_owned(),
+            }],
+        });
+        server.srcs.wait_indexed();
+        let items = pull(previous());
+        assert!(!full(&items[&other]).unwrap().contains(&lint));
+        assert!(full(&items[&top]).is_none());
+    }
+
+    #[test]
+    fn test_pull_not_published() {
+        let server = LSPServer::new(None);
+        server.pull_diagnostics.store(true, Ordering::SeqCst);
+        let uri = Url::parse("file:///top.sv").unwrap();
+        let published = server.did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri.clone(),
+                "systemverilog".to_owned(),
+                1,
+                "module top; sub u_sub(); endmodule\n".to_owned(),
+            ),
+        });
+        assert!(published.is_empty());
+        assert!(server
+            .did_save(DidSaveTextDocumentParams {
+                text_document: TextDocumentIdentifier::new(uri),
+                text: None,
+            })
+            .is_empty());
+    }
+}
@@ -0,0 +1,431 @@
+use crate::definition::{GenericScope, Scope};
+use crate::server::LSPServer;
+use crate::sources::LSPSupport;
+use regex::Regex;
+use ropey::Rope;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, OnceLock};
+use sv_parser::{unwrap_locate, RefNode, SyntaxTree};
+use tower_lsp::lsp_types::*;
+
+mod duplicates;
+mod naming;
+mod params;
+mod ports;
+mod rules;
+mod signals;
+mod unresolved;
+
+/// the file being checked and what is known about the workspace
+pub struct Context<'a> {
+    pub tree: &'a SyntaxTree,
+    pub path: &'a Path,
+    pub uri: &'a Url,
+    pub rope: &'a Rope,
+    pub scope_tree: &'a GenericScope,
+    // names and definitions of the workspace, None while files are being indexed
+    pub workspace: Option<&'a Workspace>,
+}
+
+/// the names and definitions of every file in the workspace
+#[derive(Default)]
+pub struct Workspace {
+    pub names: WorkspaceNames,
+    // modules, interfaces, programs, packages, classes and macros by namespace and name
+    pub definitions: HashMap<(&'static str, String), Vec<Location>>,
+    // a hash of the declarations of each module and interface, the checks of the files
+    // instantiating them read their ports and parameters
+    units: HashMap<String, u64>,
+}
+
+/// the diagnostics of the semantic checks of a file
+#[derive(Default)]
+pub struct SemanticReport {
+    pub diagnostics: Vec<Diagnostic>,
+    // the names the checks looked up in other files, None if they ran while files were being
+    // indexed
+    pub dependencies: Option<Vec<String>>,
+}
+
+impl LSPServer {
+    /// diagnostics that resolve names against the whole workspace, ex. instances of unknown
+    /// modules
+    pub fn semantic_diagnostics(&self, uri: &Url) -> Vec<Diagnostic> {
+        self.semantic_report(uri).diagnostics
+    }
+
+    /// the semantic diagnostics of a file and what they depend on in other files
+    pub fn semantic_report(&self, uri: &Url) -> SemanticReport {
+        self.semantic(uri).unwrap_or_default()
+    }
+
+    /// a hash of the declarations of names in the workspace, the semantic diagnostics of a file
+    /// only change with the text of the file or the signature of its dependencies. None while
+    /// files are being indexed
+    pub fn dependency_signature(&self, names: &[String]) -> Option<u64> {
+        Some(self.workspace()?.signature(names))
+    }
+
+    fn semantic(&self, uri: &Url) -> Option<SemanticReport> {
+        let file_id = *self.srcs.names.read().ok()?.get(uri)?;
+        self.srcs.wait_parse_ready(file_id, true);
+        let workspace = self.workspace();
+        let file = self.srcs.get_file(file_id)?;
+        let file = file.read().ok()?;
+        let (rtl_rules, naming) = {
+            let conf = self.conf.read().ok()?;
+            (conf.rtl_rules.enabled, conf.naming.clone())
+        };
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let path = uri.to_file_path().ok()?;
+        let cx = Context {
+            tree: file.syntax_tree.as_ref()?,
+            path: &path,
+            uri,
+            rope: &file.text,
+            scope_tree: scope_tree.as_ref()?,
+            workspace: workspace.as_deref(),
+        };
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        diagnostics.append(&mut unresolved::unresolved(&cx));
+        diagnostics.append(&mut ports::ports(&cx));
+        diagnostics.append(&mut params::params(&cx));
+        diagnostics.append(&mut duplicates::duplicates(&cx));
+        diagnostics.append(&mut signals::signals(&cx));
+        if rtl_rules {
+            diagnostics.append(&mut rules::rules(&cx));
+        }
+        diagnostics.append(&mut naming::naming(&cx, &naming));
+        Some(SemanticReport {
+            diagnostics,
+            dependencies: workspace.as_deref().map(|x| x.dependencies(&cx)),
+        })
+    }
+
+    /// the names and definitions of the workspace, built once for each generation of the
+    /// sources. None while files are being indexed, as a name can't be reported as unknown or
+    /// duplicated before every file is indexed
+    fn workspace(&self) -> Option<Arc<Workspace>> {
+        let generation = self.srcs.generation.load(Ordering::SeqCst);
+        if let Some((built, workspace)) = &*self.workspace.lock().ok()? {
+            if *built == generation {
+                return Some(workspace.clone());
+            }
+        }
+        if !self.srcs.is_indexed() {
+            return None;
+        }
+        let files = self.srcs.files.read().ok()?.clone();
+        let texts: HashMap<Url, Rope> = files
+            .iter()
+            .filter_map(|x| {
+                let file = x.read().ok()?;
+                Some((file.uri.clone(), file.text.clone()))
+            })
+            .collect();
+        let scope_tree = self.srcs.scope_tree.read().ok()?;
+        let workspace = Arc::new(Workspace::new(scope_tree.as_ref()?, &texts));
+        drop(scope_tree);
+        *self.workspace.lock().ok()? = Some((generation, workspace.clone()));
+        Some(workspace)
+    }
+}
+
+impl Workspace {
+    pub fn new(scope_tree: &GenericScope, texts: &HashMap<Url, Rope>) -> Self {
+        let mut names = WorkspaceNames::default();
+        names.add_scope_tree(scope_tree);
+        // names declared in files that couldn't be indexed are found in their text
+        let mut indexed: HashSet<Url> = HashSet::new();
+        indexed.extend(scope_tree.scopes.iter().map(|x| x.url()));
+        indexed.extend(scope_tree.defs.iter().map(|x| x.url()));
+        for (uri, text) in texts {
+            if !indexed.contains(uri) {
+                names.add_text(&text.to_string());
+            }
+        }
+        let mut units: HashMap<String, u64> = HashMap::new();
+        for unit in &scope_tree.scopes {
+            if !matches!(
+                unit.symbol_kind(),
+                SymbolKind::MODULE | SymbolKind::INTERFACE
+            ) {
+                continue;
+            }
+            let mut hasher = DefaultHasher::new();
+            for def in unit.defs() {
+                def.ident().hash(&mut hasher);
+                def.type_str().hash(&mut hasher);
+                format!("{:?}", def.def_type()).hash(&mut hasher);
+                format!("{:?}", def.symbol_kind()).hash(&mut hasher);
+                unit.is_local_parameter(def.as_ref()).hash(&mut hasher);
+            }
+            // a unit declared more than once changes with any of its declarations
+            let hash = units.entry(unit.ident()).or_default();
+            *hash = hash.wrapping_add(hasher.finish());
+        }
+        Workspace {
+            names,
+            definitions: duplicates::definitions(scope_tree, texts),
+            units,
+        }
+    }
+
+    /// the names the checks of a file look up in other files: the units it instantiates, the
+    /// packages it imports, the interfaces it uses as ports and the names it defines
+    fn dependencies(&self, cx: &Context) -> Vec<String> {
+        let mut names: HashSet<String> = HashSet::new();
+        for node in cx.tree {
+            if let Some((ident, _, _)) = unresolved::used_name(node) {
+                names.extend(cx.ident(ident));
+            }
+        }
+        for ((_, name), locations) in &self.definitions {
+            if locations.iter().any(|x| &x.uri == cx.uri) {
+                names.insert(name.clone());
+            }
+        }
+        let mut names: Vec<String> = names.into_iter().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// a hash of what the workspace declares with these names
+    fn signature(&self, names: &[String]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for name in names {
+            name.hash(&mut hasher);
+            self.names.modules.contains(name).hash(&mut hasher);
+            self.names.interfaces.contains(name).hash(&mut hasher);
+            self.names.packages.contains(name).hash(&mut hasher);
+            self.names.others.contains(name).hash(&mut hasher);
+            for namespace in ["module", "package", "class", "macro"] {
+                if let Some(locations) = self.definitions.get(&(namespace, name.clone())) {
+                    format!("{:?}", locations).hash(&mut hasher);
+                }
+            }
+            self.units.get(name).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+/// names declared at the top level of the workspace
+#[derive(Default)]
+pub struct WorkspaceNames {
+    // anything that can be instantiated: modules, interfaces, programs, primitives
+    pub modules: HashSet<String>,
+    pub interfaces: HashSet<String>,
+    pub packages: HashSet<String>,
+    // every other declared name, sv-parser can't tell a port of a user defined type from an
+    // interface port
+    pub others: HashSet<String>,
+}
+
+impl WorkspaceNames {
+    fn add_scope_tree(&mut self, scope_tree: &GenericScope) {
+        for scope in &scope_tree.scopes {
+            match scope.symbol_kind() {
+                SymbolKind::MODULE => {
+                    self.modules.insert(scope.ident());
+                }
+                SymbolKind::INTERFACE => {
+                    self.modules.insert(scope.ident());
+                    self.interfaces.insert(scope.ident());
+                }
+                SymbolKind::PACKAGE => {
+                    self.packages.insert(scope.ident());
+                }
+                _ => {
+                    self.others.insert(scope.ident());
+                }
+            }
+            self.add_nested(scope.as_ref());
+        }
+        self.others
+            .extend(scope_tree.defs.iter().map(|x| x.ident()));
+    }
+
+    fn add_nested(&mut self, scope: &dyn Scope) {
+        self.others.extend(scope.defs().iter().map(|x| x.ident()));
+        for scope in scope.scopes() {
+            self.others.insert(scope.ident());
+            self.add_nested(scope.as_ref());
+        }
+    }
+
+    /// add the declarations found in the text of a file that couldn't be parsed
+    fn add_text(&mut self, text: &str) {
+        static UNITS: OnceLock<Regex> = OnceLock::new();
+        static TYPES: OnceLock<Regex> = OnceLock::new();
+        let units = UNITS.get_or_init(|| {
+            Regex::new(r"(?m)^\s*(?:extern\s+)?(?P<kind>module|macromodule|interface|program|primitive|package|class)\s+(?:(?:static|automatic)\s+)?(?P<name>[a-zA-Z_]\w*)").unwrap()
+        });
+        // the name before the semicolon of a typedef, or after the closing brace of a struct
+        let types = TYPES.get_or_init(|| {
+            Regex::new(r"(?:\btypedef\b[^;{]*|\})\s*\b(?P<name>[a-zA-Z_]\w*)\s*(?:\[[^\]]*\]\s*)*;")
+                .unwrap()
+        });
+        for caps in units.captures_iter(text) {
+            let name = caps["name"].to_owned();
+            match &caps["kind"] {
+                "module" | "macromodule" | "program" | "primitive" => {
+                    self.modules.insert(name);
+                }
+                "interface" if name != "class" => {
+                    self.modules.insert(name.clone());
+                    self.interfaces.insert(name);
+                }
+                "package" => {
+                    self.packages.insert(name);
+                }
+                _ => {
+                    self.others.insert(name);
+                }
+            }
+        }
+        for caps in types.captures_iter(text) {
+            self.others.insert(caps["name"].to_owned());
+        }
+    }
+}
+
+impl Context<'_> {
+    /// the name and range of an identifier, None if it comes from another file, ex. an
+    /// include or a macro
+    fn ident_range(&self, node: RefNode) -> Option<(String, Range)> {
+        let loc = unwrap_locate!(node)?;
+        let ident = self.tree.get_str(loc)?.to_owned();
+        let (origin, byte_idx) = self.tree.get_origin(loc)?;
+        if origin != self.path {
+            return None;
+        }
+        let range = Range::new(
+            self.rope.byte_to_pos(byte_idx),
+            self.rope.byte_to_pos(byte_idx + loc.len),
+        );
+        Some((ident, range))
+    }
+
+    /// the text of an identifier
+    fn ident(&self, node: RefNode) -> Option<String> {
+        Some(self.tree.get_str(unwrap_locate!(node)?)?.to_owned())
+    }
+
+    /// the module, interface or program declared with a name
+    fn design_unit(&self, name: &str) -> Option<&dyn Scope> {
+        self.scope_tree
+            .scopes
+            .iter()
+            .find(|x| {
+                x.ident() == name
+                    && matches!(x.symbol_kind(), SymbolKind::MODULE | SymbolKind::INTERFACE)
+            })
+            .map(|x| x.as_ref())
+    }
+
+    /// the module, interface or program of this file containing a position
+    fn enclosing_unit(&self, pos: &Position) -> Option<&dyn Scope> {
+        let byte_idx = self.rope.pos_to_byte(pos);
+        self.scope_tree
+            .scopes
+            .iter()
+            .find(|x| {
+                &x.url() == self.uri
+                    && x.start() <= byte_idx
+                    && byte_idx <= x.end()
+                    && matches!(x.symbol_kind(), SymbolKind::MODULE | SymbolKind::INTERFACE)
+            })
+            .map(|x| x.as_ref())
+    }
+}
+
+fn diagnostic(
+    range: Range,
+    severity: DiagnosticSeverity,
+    code: &str,
+    message: String,
+) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        code: Some(NumberOrString::String(code.to_owned())),
+        source: Some("veridian".to_owned()),
+        message,
+        ..Default::default()
+    }
+}
+
+/// run a check on the last of several files, with the workspace made of all of them
+#[cfg(test)]
+fn check(files: &[(&str, &str)], check: impl Fn(&Context) -> Vec<Diagnostic>) -> Vec<Diagnostic> {
+    use crate::definition::get_scopes;
+    use std::path::PathBuf;
+    let mut scope_tree = GenericScope::new(&Url::parse("file:///").unwrap());
+    let mut trees = Vec::new();
+    for (name, text) in files {
+        let path = PathBuf::from(name);
+        let url = Url::from_file_path(&path).unwrap();
+        let (tree, _) =
+            sv_parser::parse_sv_str(text, &path, &HashMap::new(), &Vec::<PathBuf>::new(), false)
+                .unwrap();
+        let mut scopes = get_scopes(&tree, &url).unwrap();
+        scope_tree.scopes.append(&mut scopes.scopes);
+        scope_tree.defs.append(&mut scopes.defs);
+        trees.push((path, url, tree, Rope::from_str(text)));
+    }
+    let texts: HashMap<Url, Rope> = trees.iter().map(|x| (x.1.clone(), x.3.clone())).collect();
+    let workspace = Workspace::new(&scope_tree, &texts);
+    let (path, uri, tree, rope) = trees.last().unwrap();
+    check(&Context {
+        tree,
+        path,
+        uri,
+        rope,
+        scope_tree: &scope_tree,
+        workspace: Some(&workspace),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_cache() {
+        let server = LSPServer::new(None);
+        let uri = Url::parse("file:///top.sv").unwrap();
+        server.srcs.add(TextDocumentItem::new(
+            uri.clone(),
+            "systemverilog".to_owned(),
+            1,
+            "module top;\n  sub u_sub();\nendmodule\n".to_owned(),
+        ));
+        server.srcs.wait_indexed();
+        let codes = || -> Vec<Option<NumberOrString>> {
+            server
+                .semantic_diagnostics(&uri)
+                .into_iter()
+                .map(|x| x.code)
+                .collect()
+        };
+        assert_eq!(
+            codes(),
+            vec![Some(NumberOrString::String("unresolved-module".to_owned()))]
+        );
+        // the workspace is only found again when a source changes
+        let workspace = server.workspace().unwrap();
+        assert!(Arc::ptr_eq(&workspace, &server.workspace().unwrap()));
+        // while a file is being indexed, names aren't reported as unknown
+        *server.srcs.unparsed.0.lock().unwrap() += 1;
+        server.srcs.generation.fetch_add(1, Ordering::SeqCst);
+        assert!(server.workspace().is_none());
+        assert!(codes().is_empty());
+        *server.srcs.unparsed.0.lock().unwrap() -= 1;
+        assert!(!Arc::ptr_eq(&workspace, &server.workspace().unwrap()));
+    }
+}
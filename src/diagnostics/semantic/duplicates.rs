@@ -0,0 +1,175 @@
+use super::{diagnostic, Context};
+use crate::definition::GenericScope;
+use crate::sources::LSPSupport;
+use regex::Regex;
+use ropey::Rope;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use tower_lsp::lsp_types::*;
+
+/// report modules, interfaces, programs, packages, classes and macros of this file that are
+/// defined again elsewhere in the workspace
+pub fn duplicates(cx: &Context) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let Some(workspace) = cx.workspace else {
+        return diagnostics;
+    };
+    for ((_, name), locations) in &workspace.definitions {
+        // a name defined twice in one file is left to the compiler, ex. under `ifdef
+        let uris: HashSet<&Url> = locations.iter().map(|x| &x.uri).collect();
+        if uris.len() < 2 {
+            continue;
+        }
+        let others: Vec<&Location> = locations.iter().filter(|x| &x.uri != cx.uri).collect();
+        let mut files: Vec<&str> = others
+            .iter()
+            .filter_map(|x| x.uri.path_segments()?.next_back())
+            .collect();
+        files.sort_unstable();
+        files.dedup();
+        for loc in locations.iter().filter(|x| &x.uri == cx.uri) {
+            let mut diag = diagnostic(
+                loc.range,
+                DiagnosticSeverity::WARNING,
+                "duplicate-definition",
+                format!("`{}` is also defined in {}", name, files.join(", ")),
+            );
+            diag.related_information = Some(
+                others
+                    .iter()
+                    .map(|x| DiagnosticRelatedInformation {
+                        location: (*x).clone(),
+                        message: format!("other definition of `{}`", name),
+                    })
+                    .collect(),
+            );
+            diagnostics.push(diag);
+        }
+    }
+    diagnostics.sort_by_key(|x| x.range.start);
+    diagnostics
+}
+
+/// the definitions of the workspace by namespace and name, modules, interfaces and programs
+/// share a namespace
+pub(super) fn definitions(
+    scope_tree: &GenericScope,
+    texts: &HashMap<Url, Rope>,
+) -> HashMap<(&'static str, String), Vec<Location>> {
+    let mut definitions: HashMap<(&'static str, String), Vec<Location>> = HashMap::new();
+    for scope in &scope_tree.scopes {
+        let namespace = match scope.symbol_kind() {
+            SymbolKind::MODULE | SymbolKind::INTERFACE => "module",
+            SymbolKind::PACKAGE => "package",
+            SymbolKind::CLASS => "class",
+            _ => "",
+        };
+        if !namespace.is_empty() {
+            if let Some(loc) = location(texts, &scope.url(), &scope.ident(), scope.byte_idx()) {
+                definitions
+                    .entry((namespace, scope.ident()))
+                    .or_default()
+                    .push(loc);
+            }
+        }
+    }
+    macros(texts, &mut definitions);
+    definitions
+}
+
+/// add the macros defined in every file, the preprocessor removes them from the syntax tree
+fn macros(
+    texts: &HashMap<Url, Rope>,
+    definitions: &mut HashMap<(&'static str, String), Vec<Location>>,
+) {
+    static DEFINE: OnceLock<Regex> = OnceLock::new();
+    let define = DEFINE
+        .get_or_init(|| Regex::new(r"(?m)^[ \t]*`define[ \t]+(?P<name>[a-zA-Z_]\w*)").unwrap());
+    for (uri, text) in texts {
+        for caps in define.captures_iter(&text.to_string()) {
+            let name = caps.name("name").unwrap();
+            let range = Range::new(text.byte_to_pos(name.start()), text.byte_to_pos(name.end()));
+            definitions
+                .entry(("macro", name.as_str().to_owned()))
+                .or_default()
+                .push(Location::new(uri.clone(), range));
+        }
+    }
+}
+
+/// the location of a definition, None if it isn't in the file it is indexed for. Definitions
+/// from included files are indexed for every file including them, with positions in the
+/// included file
+fn location(
+    texts: &HashMap<Url, Rope>,
+    uri: &Url,
+    ident: &str,
+    byte_idx: usize,
+) -> Option<Location> {
+    let text = texts.get(uri)?;
+    let end = byte_idx + ident.len();
+    if end > text.len_bytes() || text.byte_slice(byte_idx..end) != ident {
+        return None;
+    }
+    Some(Location::new(
+        uri.clone(),
+        Range::new(text.byte_to_pos(byte_idx), text.byte_to_pos(end)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::check;
+    use super::*;
+
+    #[test]
+    fn test_duplicates() {
+        let lib = r#"`define WIDTH 8
+module fifo;
+endmodule
+package pkg;
+endpackage
+"#;
+        let text = r#"`define WIDTH 16
+module fifo;
+endmodule
+interface pkg;
+endinterface
+class fifo_item;
+endclass
+"#;
+        let diagnostics = check(&[("/lib.sv", lib), ("/top.sv", text)], duplicates);
+        let summary: Vec<(String, Range, Vec<Location>)> = diagnostics
+            .into_iter()
+            .map(|x| {
+                (
+                    x.message,
+                    x.range,
+                    x.related_information
+                        .unwrap()
+                        .into_iter()
+                        .map(|x| x.location)
+                        .collect(),
+                )
+            })
+            .collect();
+        let lib = Url::parse("file:///lib.sv").unwrap();
+        let at =
+            |line, start, end| Range::new(Position::new(line, start), Position::new(line, end));
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "`WIDTH` is also defined in lib.sv".to_owned(),
+                    at(0, 8, 13),
+                    vec![Location::new(lib.clone(), at(0, 8, 13))]
+                ),
+                (
+                    "`fifo` is also defined in lib.sv".to_owned(),
+                    at(1, 7, 11),
+                    vec![Location::new(lib, at(1, 7, 11))]
+                ),
+            ]
+        );
+    }
+}
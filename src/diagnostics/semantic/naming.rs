@@ -0,0 +1,331 @@
+use super::{diagnostic, Context};
+use crate::definition::{Definition, DefinitionType, Scope};
+use crate::server::Naming;
+use crate::sources::LSPSupport;
+use log::warn;
+use regex::Regex;
+use std::collections::HashSet;
+use sv_parser::*;
+use tower_lsp::lsp_types::*;
+
+/// report declarations of this file whose names don't match the naming conventions, with a
+/// name that does attached for the rename quick fix
+pub fn naming(cx: &Context, conf: &Naming) -> Vec<Diagnostic> {
+    let rules = Rules {
+        modules: rule(&conf.modules),
+        ports: rule(&conf.ports),
+        nets: rule(&conf.nets),
+        variables: rule(&conf.variables),
+        subroutines: rule(&conf.subroutines),
+        parameters: rule(&conf.parameters),
+    };
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for scope in &cx.scope_tree.scopes {
+        if &scope.url() != cx.uri {
+            continue;
+        }
+        if scope.symbol_kind() == SymbolKind::MODULE {
+            diagnostics.extend(convention(cx, "module", &rules.modules, scope.as_ref()));
+        }
+        rules.scope(cx, scope.as_ref(), &mut diagnostics);
+    }
+    diagnostics.append(&mut clocks(cx, conf));
+    diagnostics
+}
+
+/// the naming conventions of each kind of declaration
+struct Rules {
+    modules: Option<Regex>,
+    ports: Option<Regex>,
+    nets: Option<Regex>,
+    variables: Option<Regex>,
+    subroutines: Option<Regex>,
+    parameters: Option<Regex>,
+}
+
+impl Rules {
+    fn scope(&self, cx: &Context, scope: &dyn Scope, diagnostics: &mut Vec<Diagnostic>) {
+        for def in scope.defs() {
+            let (kind, rule) = match (def.def_type(), def.symbol_kind()) {
+                (DefinitionType::Port, _) => ("port", &self.ports),
+                (_, SymbolKind::TYPE_PARAMETER) => ("parameter", &self.parameters),
+                // nets and variables are both indexed as variables
+                (_, SymbolKind::VARIABLE) if is_net(&def.type_str()) => ("net", &self.nets),
+                (_, SymbolKind::VARIABLE) => ("variable", &self.variables),
+                _ => continue,
+            };
+            diagnostics.extend(convention(cx, kind, rule, def.as_ref()));
+        }
+        for scope in scope.scopes() {
+            if matches!(scope.def_type(), DefinitionType::Subroutine) {
+                diagnostics.extend(convention(
+                    cx,
+                    "subroutine",
+                    &self.subroutines,
+                    scope.as_ref(),
+                ));
+            }
+            self.scope(cx, scope.as_ref(), diagnostics);
+        }
+    }
+}
+
+fn is_net(type_str: &str) -> bool {
+    matches!(
+        type_str.split_whitespace().next(),
+        Some(
+            "wire"
+                | "uwire"
+                | "tri"
+                | "tri0"
+                | "tri1"
+                | "triand"
+                | "trior"
+                | "trireg"
+                | "wand"
+                | "wor"
+                | "supply0"
+                | "supply1"
+                | "interconnect"
+        )
+    )
+}
+
+/// an empty convention isn't checked
+fn rule(re: &str) -> Option<Regex> {
+    if re.is_empty() {
+        return None;
+    }
+    match Regex::new(re) {
+        Ok(re) => Some(re),
+        Err(e) => {
+            warn!("invalid naming convention {}: {}", re, e);
+            None
+        }
+    }
+}
+
+fn convention<T: Definition + ?Sized>(
+    cx: &Context,
+    kind: &str,
+    rule: &Option<Regex>,
+    def: &T,
+) -> Option<Diagnostic> {
+    let rule = rule.as_ref()?;
+    let name = def.ident();
+    // definitions from included files have positions in the included file
+    let end = def.byte_idx() + name.len();
+    if end > cx.rope.len_bytes() || cx.rope.byte_slice(def.byte_idx()..end) != name.as_str() {
+        return None;
+    }
+    let range = Range::new(
+        cx.rope.byte_to_pos(def.byte_idx()),
+        cx.rope.byte_to_pos(end),
+    );
+    violation(kind, rule, &name, range)
+}
+
+fn violation(kind: &str, rule: &Regex, name: &str, range: Range) -> Option<Diagnostic> {
+    if rule.is_match(name) {
+        return None;
+    }
+    let mut diag = diagnostic(
+        range,
+        DiagnosticSeverity::WARNING,
+        "naming-convention",
+        format!(
+            "{} `{}` does not match the naming convention `{}`",
+            kind,
+            name,
+            rule.as_str()
+        ),
+    );
+    diag.data = suggestions(name)
+        .into_iter()
+        .find(|x| rule.is_match(x))
+        .map(serde_json::Value::String);
+    Some(diag)
+}
+
+/// clocks are the first edge a sequential block is triggered on, and asynchronous resets the
+/// edges after it
+fn clocks(cx: &Context, conf: &Naming) -> Vec<Diagnostic> {
+    let clock = rule(&conf.clocks);
+    let reset = rule(&conf.resets);
+    let active_low = rule(&conf.active_low);
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    if clock.is_none() && reset.is_none() && active_low.is_none() {
+        return diagnostics;
+    }
+    let mut checked: HashSet<String> = HashSet::new();
+    for node in cx.tree {
+        let RefNode::AlwaysConstruct(x) = node else {
+            continue;
+        };
+        let StatementItem::ProceduralTimingControlStatement(x) = &x.nodes.1.nodes.2 else {
+            continue;
+        };
+        let ProceduralTimingControl::EventControl(x) = &x.nodes.0 else {
+            continue;
+        };
+        let EventControl::EventExpression(x) = &**x else {
+            continue;
+        };
+        let mut first = true;
+        for node in RefNode::EventControlEventExpression(x) {
+            let RefNode::EventExpressionExpression(x) = node else {
+                continue;
+            };
+            let Some(edge) = &x.nodes.0 else {
+                continue;
+            };
+            let Some(ident) = RefNode::Expression(&x.nodes.1)
+                .into_iter()
+                .find(|x| matches!(x, RefNode::Identifier(_)))
+            else {
+                continue;
+            };
+            let Some((name, range)) = cx.ident_range(ident) else {
+                continue;
+            };
+            let negedge = matches!(edge, EdgeIdentifier::Negedge(_));
+            let rules = if first {
+                vec![("clock", &clock)]
+            } else {
+                vec![("reset", &reset), ("active-low reset", &active_low)]
+            };
+            first = false;
+            if !checked.insert(name.clone()) {
+                continue;
+            }
+            for (kind, rule) in rules {
+                if kind == "active-low reset" && !negedge {
+                    continue;
+                }
+                if let Some(rule) = rule {
+                    diagnostics.extend(violation(kind, rule, &name, range));
+                }
+            }
+        }
+    }
+    diagnostics
+}
+
+/// the name in other cases and with an active-low suffix, ex. `dataIn` as `data_in`, `DATA_IN`,
+/// `DataIn` and `data_in_n`
+fn suggestions(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if (c == '_' || (c.is_uppercase() && prev_lower)) && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c != '_' {
+            word.extend(c.to_lowercase());
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    let capitalize = |x: &String| {
+        let mut chars = x.chars();
+        chars
+            .next()
+            .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default()
+    };
+    let snake = words.join("_");
+    let pascal: String = words.iter().map(capitalize).collect();
+    let camel = match words.split_first() {
+        Some((first, rest)) => first.clone() + &rest.iter().map(capitalize).collect::<String>(),
+        None => String::new(),
+    };
+    let cases = vec![snake.clone(), snake.to_uppercase(), pascal, camel];
+    let mut suggestions = cases.clone();
+    suggestions.extend(cases.iter().map(|x| format!("{}_n", x)));
+    suggestions.retain(|x| !x.is_empty() && x != name);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::check;
+    use super::*;
+
+    #[test]
+    fn test_naming() {
+        let text = r#"module TopLevel #(parameter width = 8) (input logic Clk, input logic rst, output logic [width-1:0] dataOut);
+  logic [width-1:0] count_q;
+  wire Ready;
+  function automatic logic Parity(input logic [7:0] x);
+    return ^x;
+  endfunction
+  always_ff @(posedge Clk or negedge rst) dataOut <= count_q;
+endmodule
+"#;
+        let conf = Naming {
+            modules: "^[a-z][a-z0-9_]*$".to_owned(),
+            ports: "^[a-z][a-z0-9_]*$".to_owned(),
+            nets: "^[a-z][a-z0-9_]*$".to_owned(),
+            variables: "_q$".to_owned(),
+            subroutines: "^[a-z][a-z0-9_]*$".to_owned(),
+            parameters: "^[A-Z][A-Z0-9_]*$".to_owned(),
+            clocks: "^clk".to_owned(),
+            resets: String::new(),
+            active_low: "_n$".to_owned(),
+        };
+        let diagnostics: Vec<(String, Option<String>)> =
+            check(&[("/top.sv", text)], |cx| naming(cx, &conf))
+                .into_iter()
+                .map(|x| {
+                    (
+                        x.message,
+                        x.data.and_then(|x| x.as_str().map(str::to_owned)),
+                    )
+                })
+                .collect();
+        let expected: Vec<(&str, Option<&str>)> = vec![
+            (
+                "module `TopLevel` does not match the naming convention `^[a-z][a-z0-9_]*$`",
+                Some("top_level"),
+            ),
+            (
+                "parameter `width` does not match the naming convention `^[A-Z][A-Z0-9_]*$`",
+                Some("WIDTH"),
+            ),
+            (
+                "port `Clk` does not match the naming convention `^[a-z][a-z0-9_]*$`",
+                Some("clk"),
+            ),
+            (
+                "port `dataOut` does not match the naming convention `^[a-z][a-z0-9_]*$`",
+                Some("data_out"),
+            ),
+            (
+                "net `Ready` does not match the naming convention `^[a-z][a-z0-9_]*$`",
+                Some("ready"),
+            ),
+            (
+                "subroutine `Parity` does not match the naming convention `^[a-z][a-z0-9_]*$`",
+                Some("parity"),
+            ),
+            (
+                "clock `Clk` does not match the naming convention `^clk`",
+                Some("clk"),
+            ),
+            (
+                "active-low reset `rst` does not match the naming convention `_n$`",
+                Some("rst_n"),
+            ),
+        ];
+        assert_eq!(
+            diagnostics,
+            expected
+                .into_iter()
+                .map(|(x, y)| (x.to_owned(), y.map(str::to_owned)))
+                .collect::<Vec<(String, Option<String>)>>()
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use super::{diagnostic, Context};
+use std::collections::HashSet;
+use sv_parser::*;
+use tower_lsp::lsp_types::*;
+
+/// check the parameter overrides of module, interface and program instances against the
+/// parameters they are declared with
+pub fn params(cx: &Context) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for node in cx.tree {
+        let (ident, overrides) = match node {
+            RefNode::ModuleInstantiation(x) => (RefNode::ModuleIdentifier(&x.nodes.0), &x.nodes.1),
+            RefNode::InterfaceInstantiation(x) => {
+                (RefNode::InterfaceIdentifier(&x.nodes.0), &x.nodes.1)
+            }
+            RefNode::ProgramInstantiation(x) => {
+                (RefNode::ProgramIdentifier(&x.nodes.0), &x.nodes.1)
+            }
+            _ => continue,
+        };
+        let Some(overrides) = overrides.as_ref().and_then(|x| x.nodes.1.nodes.1.as_ref()) else {
+            continue;
+        };
+        let Some((name, unit_range)) = cx.ident_range(ident) else {
+            continue;
+        };
+        let Some(unit) = cx.design_unit(&name) else {
+            continue;
+        };
+        // without localparams and the parameters declared in the body of a unit with a parameter
+        // port list, overrides by position assign the parameter port list
+        let overridable = unit.parameters();
+        match overrides {
+            ListOfParameterAssignments::Ordered(x) => {
+                let count = x.nodes.0.contents().len();
+                if count > overridable.len() {
+                    diagnostics.push(diagnostic(
+                        unit_range,
+                        DiagnosticSeverity::ERROR,
+                        "parameter-count",
+                        format!(
+                            "`{}` has {} overridable parameter{}, but {} are assigned by position",
+                            name,
+                            overridable.len(),
+                            if overridable.len() == 1 { "" } else { "s" },
+                            count
+                        ),
+                    ));
+                }
+            }
+            ListOfParameterAssignments::Named(x) => {
+                let mut assigned: HashSet<String> = HashSet::new();
+                for assignment in x.nodes.0.contents() {
+                    let Some((param, range)) =
+                        cx.ident_range(RefNode::ParameterIdentifier(&assignment.nodes.1))
+                    else {
+                        continue;
+                    };
+                    if !assigned.insert(param.clone()) {
+                        diagnostics.push(diagnostic(
+                            range,
+                            DiagnosticSeverity::ERROR,
+                            "duplicate-parameter",
+                            format!("parameter `{}` is assigned more than once", param),
+                        ));
+                    } else if unit
+                        .defs()
+                        .iter()
+                        .any(|x| x.ident() == param && unit.is_local_parameter(x.as_ref()))
+                    {
+                        diagnostics.push(diagnostic(
+                            range,
+                            DiagnosticSeverity::ERROR,
+                            "localparam-override",
+                            format!(
+                                "`{}` is a localparam of `{}` and can't be overridden",
+                                param, name
+                            ),
+                        ));
+                    } else if !overridable.iter().any(|x| x.ident() == param) {
+                        diagnostics.push(diagnostic(
+                            range,
+                            DiagnosticSeverity::ERROR,
+                            "unknown-parameter",
+                            format!("`{}` has no parameter `{}`", name, param),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::check;
+    use super::*;
+
+    #[test]
+    fn test_params() {
+        // with a parameter port list, parameters declared in the body are localparams
+        let lib = r#"module fifo #(parameter W = 8, N = 1, localparam D = 2 * W) (input logic clk);
+  parameter int DEPTH = 4;
+  localparam int AW = $clog2(DEPTH);
+endmodule
+module ram;
+  parameter int DEPTH = 4;
+endmodule
+module rom #(parameter A) ();
+  parameter B = 2;
+endmodule
+"#;
+        let text = r#"module top;
+  logic clk;
+  fifo #(.W(16), .DEPTH(2)) u_a(.clk);
+  fifo #(.W(16), .W(8), .D(1), .AW(2), .X(0)) u_b(.clk);
+  fifo #(16, 2, 1) u_c(.clk);
+  ram #(.DEPTH(8)) u_d();
+  rom #(1, 2) u_e();
+  ram #(8) u_f();
+endmodule
+"#;
+        let diagnostics: Vec<(String, Range)> =
+            check(&[("/lib.sv", lib), ("/top.sv", text)], params)
+                .into_iter()
+                .map(|x| (x.message, x.range))
+                .collect();
+        let at =
+            |line, start, end| Range::new(Position::new(line, start), Position::new(line, end));
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    "`DEPTH` is a localparam of `fifo` and can't be overridden".to_owned(),
+                    at(2, 18, 23)
+                ),
+                (
+                    "parameter `W` is assigned more than once".to_owned(),
+                    at(3, 18, 19)
+                ),
+                (
+                    "`D` is a localparam of `fifo` and can't be overridden".to_owned(),
+                    at(3, 25, 26)
+                ),
+                (
+                    "`AW` is a localparam of `fifo` and can't be overridden".to_owned(),
+                    at(3, 32, 34)
+                ),
+                ("`fifo` has no parameter `X`".to_owned(), at(3, 40, 41)),
+                (
+                    "`fifo` has 2 overridable parameters, but 3 are assigned by position"
+                        .to_owned(),
+                    at(4, 2, 6)
+                ),
+                (
+                    "`rom` has 1 overridable parameter, but 2 are assigned by position".to_owned(),
+                    at(6, 2, 5)
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use super::{diagnostic, Context};
+use crate::definition::{Definition, DefinitionType};
+use std::collections::HashSet;
+use sv_parser::*;
+use tower_lsp::lsp_types::*;
+
+/// check the port connections of module, interface and program instances against the ports
+/// they are declared with
+pub fn ports(cx: &Context) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for node in cx.tree {
+        let (ident, instances) = match node {
+            RefNode::ModuleInstantiation(x) => (RefNode::ModuleIdentifier(&x.nodes.0), &x.nodes.2),
+            RefNode::InterfaceInstantiation(x) => {
+                (RefNode::InterfaceIdentifier(&x.nodes.0), &x.nodes.2)
+            }
+            RefNode::ProgramInstantiation(x) => {
+                (RefNode::ProgramIdentifier(&x.nodes.0), &x.nodes.2)
+            }
+            _ => continue,
+        };
+        let Some(name) = cx.ident(ident) else {
+            continue;
+        };
+        let Some(unit) = cx.design_unit(&name) else {
+            continue;
+        };
+        let ports: Vec<&dyn Definition> = unit
+            .defs()
+            .iter()
+            .filter(|x| matches!(x.def_type(), DefinitionType::Port))
+            .map(|x| x.as_ref())
+            .collect();
+        // the ports of modules declared with .* or in a way that isn't indexed are unknown
+        if ports.is_empty() {
+            continue;
+        }
+        for instance in instances.contents() {
+            diagnostics.append(&mut connections(cx, &name, &ports, instance));
+        }
+    }
+    diagnostics
+}
+
+fn connections(
+    cx: &Context,
+    name: &str,
+    ports: &[&dyn Definition],
+    instance: &HierarchicalInstance,
+) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let Some((_, inst_range)) =
+        cx.ident_range(RefNode::InstanceIdentifier(&instance.nodes.0.nodes.0))
+    else {
+        return diagnostics;
+    };
+    let mut connected: HashSet<String> = HashSet::new();
+    match &instance.nodes.1.nodes.1 {
+        Some(ListOfPortConnections::Ordered(x)) => {
+            let connections = x.nodes.0.contents();
+            let count = connections.len();
+            // `u_inst()` connects no ports
+            let empty = count == 1 && connections[0].nodes.1.is_none();
+            if empty {
+                diagnostics.extend(unconnected(name, ports, &connected, inst_range));
+            } else if count != ports.len() {
+                diagnostics.push(diagnostic(
+                    inst_range,
+                    DiagnosticSeverity::ERROR,
+                    "port-count",
+                    format!(
+                        "`{}` has {} ports, but {} are connected by position",
+                        name,
+                        ports.len(),
+                        count
+                    ),
+                ));
+            }
+            return diagnostics;
+        }
+        Some(ListOfPortConnections::Named(x)) => {
+            let mut wildcard = false;
+            for connection in x.nodes.0.contents() {
+                let connection = match connection {
+                    NamedPortConnection::Identifier(x) => x,
+                    NamedPortConnection::Asterisk(_) => {
+                        wildcard = true;
+                        continue;
+                    }
+                };
+                let Some((port_name, range)) =
+                    cx.ident_range(RefNode::PortIdentifier(&connection.nodes.2))
+                else {
+                    continue;
+                };
+                let Some(port) = ports.iter().find(|x| x.ident() == port_name) else {
+                    diagnostics.push(diagnostic(
+                        range,
+                        DiagnosticSeverity::ERROR,
+                        "unknown-port",
+                        format!("`{}` has no port `{}`", name, port_name),
+                    ));
+                    continue;
+                };
+                if !connected.insert(port_name.clone()) {
+                    diagnostics.push(diagnostic(
+                        range,
+                        DiagnosticSeverity::ERROR,
+                        "duplicate-port",
+                        format!("port `{}` is connected more than once", port_name),
+                    ));
+                    continue;
+                }
+                let expr = connection.nodes.3.as_ref().and_then(|x| x.nodes.1.as_ref());
+                if direction(*port) == Some("output") {
+                    diagnostics.extend(drives_input(cx, &port_name, expr, range));
+                }
+            }
+            if !wildcard {
+                diagnostics.extend(unconnected(name, ports, &connected, inst_range));
+            }
+        }
+        None => diagnostics.extend(unconnected(name, ports, &connected, inst_range)),
+    }
+    diagnostics
+}
+
+/// the ports left unconnected, reported once for the instance
+fn unconnected(
+    name: &str,
+    ports: &[&dyn Definition],
+    connected: &HashSet<String>,
+    range: Range,
+) -> Option<Diagnostic> {
+    let missing: Vec<String> = ports
+        .iter()
+        .map(|x| x.ident())
+        .filter(|x| !connected.contains(x))
+        .collect();
+    if missing.is_empty() {
+        return None;
+    }
+    let message = if missing.len() == 1 {
+        format!("port `{}` of `{}` is not connected", missing[0], name)
+    } else {
+        format!(
+            "ports of `{}` are not connected: {}",
+            name,
+            missing
+                .iter()
+                .map(|x| format!("`{}`", x))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    };
+    Some(diagnostic(
+        range,
+        DiagnosticSeverity::WARNING,
+        "unconnected-port",
+        message,
+    ))
+}
+
+/// an output connected to an input of the enclosing module drives that input from inside
+fn drives_input(
+    cx: &Context,
+    port_name: &str,
+    expr: Option<&Expression>,
+    range: Range,
+) -> Option<Diagnostic> {
+    let (signal, _) = identifier(expr?)?;
+    let signal = cx.ident(RefNode::Identifier(signal))?;
+    let parent = cx.enclosing_unit(&range.start)?;
+
+    let input = parent.defs().iter().any(|x| {
+        matches!(x.def_type(), DefinitionType::Port)
+            && x.ident() == signal
+            && direction(x.as_ref()) == Some("input")
+    });
+    if !input {
+        return None;
+    }
+    Some(diagnostic(
+        range,
+        DiagnosticSeverity::WARNING,
+        "port-direction",
+        format!(
+            "output `{}` drives `{}`, an input of `{}`",
+            port_name,
+            signal,
+            parent.ident()
+        ),
+    ))
+}
+
+/// the direction a port is declared with, ports without one are inout
+pub(super) fn direction(port: &dyn Definition) -> Option<&'static str> {
+    let type_str = port.type_str();
+    match type_str.split_whitespace().next() {
+        Some("input") => Some("input"),
+        Some("output") => Some("output"),
+        Some("ref") => Some("ref"),
+        Some("inout") => Some("inout"),
+        _ => None,
+    }
+}
+
+/// the signal an expression consists of and its select, ex. `a` or `a[3:0]`
+pub(super) fn identifier(expr: &Expression) -> Option<(&Identifier, &Select)> {
+    let Expression::Primary(primary) = expr else {
+        return None;
+    };
+    let Primary::Hierarchical(x) = &**primary else {
+        return None;
+    };
+    let (scope, ident, select) = &x.nodes;
+    // sv-parser reports an empty class qualifier for unqualified names
+    let qualified = match scope {
+        Some(ClassQualifierOrPackageScope::ClassQualifier(x)) => {
+            x.nodes.0.is_some() || x.nodes.1.is_some()
+        }
+        Some(ClassQualifierOrPackageScope::PackageScope(_)) => true,
+        None => false,
+    };
+    let (root, path, ident) = &ident.nodes;
+    if qualified || root.is_some() || !path.is_empty() {
+        return None;
+    }
+    Some((ident, select))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::check;
+    use super::*;
+
+    #[test]
+    fn test_ports() {
+        let lib = r#"module fifo #(parameter W = 8) (
+  input logic clk,
+  input logic [W-1:0] din,
+  output logic [W-1:0] dout,
+  output logic full
+);
+endmodule
+module old(a, b, c);
+  input a;
+  output b, c;
+endmodule
+"#;
+        let text = r#"module top(input logic clk, input logic [7:0] data);
+  logic [7:0] q;
+  fifo u_a(.clk(clk), .din(data), .dout(q), .full(), .empty());
+  fifo u_b(.clk(clk), .clk(clk), .dout(data));
+  fifo u_c(.*);
+  old u_d(clk, q);
+  old u_e();
+endmodule
+"#;
+        let diagnostics: Vec<(String, Range)> =
+            check(&[("/lib.sv", lib), ("/top.sv", text)], ports)
+                .into_iter()
+                .map(|x| (x.message, x.range))
+                .collect();
+        let at =
+            |line, start, end| Range::new(Position::new(line, start), Position::new(line, end));
+        assert_eq!(
+            diagnostics,
+            vec![
+                ("`fifo` has no port `empty`".to_owned(), at(2, 54, 59)),
+                (
+                    "port `clk` is connected more than once".to_owned(),
+                    at(3, 23, 26)
+                ),
+                (
+                    "output `dout` drives `data`, an input of `top`".to_owned(),
+                    at(3, 34, 38)
+                ),
+                (
+                    "ports of `fifo` are not connected: `din`, `full`".to_owned(),
+                    at(3, 7, 10)
+                ),
+                (
+                    "`old` has 3 ports, but 2 are connected by position".to_owned(),
+                    at(5, 6, 9)
+                ),
+                (
+                    "ports of `old` are not connected: `a`, `b`, `c`".to_owned(),
+                    at(6, 6, 9)
+                ),
+            ]
+        );
+    }
+}
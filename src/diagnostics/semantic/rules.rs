@@ -0,0 +1,362 @@
+use super::signals::{misparsed, target};
+use super::{diagnostic, Context};
+use crate::definition::DefinitionType;
+use std::collections::HashSet;
+use sv_parser::*;
+use tower_lsp::lsp_types::*;
+
+/// RTL coding rules checked on the syntax tree of this file
+pub fn rules(cx: &Context) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for node in cx.tree {
+        match node {
+            RefNode::AlwaysConstruct(x) => diagnostics.append(&mut always(cx, x)),
+            RefNode::CaseKeyword(CaseKeyword::Casex(x)) => {
+                if let Some((_, range)) = cx.ident_range(RefNode::Keyword(x)) {
+                    diagnostics.push(diagnostic(
+                        range,
+                        DiagnosticSeverity::WARNING,
+                        "casex",
+                        "`casex` matches x and z as wildcards, use `casez` or `case inside`"
+                            .to_owned(),
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+    diagnostics
+}
+
+fn always(cx: &Context, x: &AlwaysConstruct) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let body = RefNode::Statement(&x.nodes.1);
+    match &x.nodes.0 {
+        AlwaysKeyword::AlwaysFf(_) => {
+            for node in body {
+                let blocking = match node {
+                    RefNode::BlockingAssignment(_) => true,
+                    RefNode::DataDeclarationVariable(x) => misparsed(x),
+                    _ => false,
+                };
+                if !blocking {
+                    continue;
+                }
+                if let Some((_, range)) = cx.ident_range(node) {
+                    diagnostics.push(diagnostic(
+                        range,
+                        DiagnosticSeverity::WARNING,
+                        "blocking-in-always-ff",
+                        "blocking assignment in `always_ff`, use `<=`".to_owned(),
+                    ));
+                }
+            }
+        }
+        AlwaysKeyword::AlwaysComb(_) => {
+            for node in body.clone() {
+                if let RefNode::NonblockingAssignment(_) = node {
+                    if let Some((_, range)) = cx.ident_range(node) {
+                        diagnostics.push(diagnostic(
+                            range,
+                            DiagnosticSeverity::WARNING,
+                            "nonblocking-in-always-comb",
+                            "nonblocking assignment in `always_comb`, use `=`".to_owned(),
+                        ));
+                    }
+                }
+            }
+            diagnostics.append(&mut combinational(cx, body));
+        }
+        AlwaysKeyword::Always(_) => {
+            let StatementItem::ProceduralTimingControlStatement(x) = &x.nodes.1.nodes.2 else {
+                return diagnostics;
+            };
+            let ProceduralTimingControl::EventControl(event) = &x.nodes.0 else {
+                return diagnostics;
+            };
+            let body = RefNode::StatementOrNull(&x.nodes.1);
+            match &**event {
+                EventControl::Asterisk(_) | EventControl::ParenAsterisk(_) => {
+                    diagnostics.append(&mut combinational(cx, body))
+                }
+                EventControl::EventExpression(events) => {
+                    let events = RefNode::EventControlEventExpression(events);
+                    // blocks triggered on an edge are sequential
+                    if events
+                        .clone()
+                        .into_iter()
+                        .any(|x| matches!(x, RefNode::EdgeIdentifier(_)))
+                    {
+                        return diagnostics;
+                    }
+                    diagnostics.append(&mut sensitivity(cx, events, body.clone()));
+                    diagnostics.append(&mut combinational(cx, body));
+                }
+                _ => (),
+            }
+        }
+        AlwaysKeyword::AlwaysLatch(_) => (),
+    }
+    diagnostics
+}
+
+/// signals of the module read by a combinational block but missing from its event list,
+/// reported at the first read of each
+fn sensitivity(cx: &Context, events: RefNode, body: RefNode) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut listed: HashSet<String> = HashSet::new();
+    for node in events {
+        if let RefNode::Identifier(_) = node {
+            listed.extend(cx.ident(node));
+        }
+    }
+    let Some((_, start)) = cx.ident_range(body.clone()) else {
+        return diagnostics;
+    };
+    let Some(unit) = cx.enclosing_unit(&start.start) else {
+        return diagnostics;
+    };
+    let signals: HashSet<String> = unit
+        .defs()
+        .iter()
+        .filter(|x| {
+            matches!(
+                x.def_type(),
+                DefinitionType::Port | DefinitionType::Net | DefinitionType::Data
+            )
+        })
+        .map(|x| x.ident())
+        .collect();
+    let assigned: HashSet<usize> = targets(cx, body.clone()).into_iter().map(|x| x.0).collect();
+    for node in body {
+        if let RefNode::Identifier(_) = node {
+            let Some(loc) = unwrap_locate!(node.clone()) else {
+                continue;
+            };
+            if assigned.contains(&loc.offset) {
+                continue;
+            }
+            let Some((name, range)) = cx.ident_range(node) else {
+                continue;
+            };
+            if signals.contains(&name) && listed.insert(name.clone()) {
+                diagnostics.push(diagnostic(
+                    range,
+                    DiagnosticSeverity::WARNING,
+                    "incomplete-sensitivity",
+                    format!("`{}` is read but missing from the sensitivity list", name),
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// the latch rules of a combinational block, sequential blocks and functions don't infer latches
+fn combinational(cx: &Context, body: RefNode) -> Vec<Diagnostic> {
+    let mut diagnostics = latches(cx, body.clone());
+    for node in body {
+        if let RefNode::CaseStatement(x) = node {
+            diagnostics.extend(case_default(cx, x));
+        }
+    }
+    diagnostics
+}
+
+/// an `if` without `else` in a combinational block infers a latch for the signals it assigns,
+/// unless they are assigned a default value before it
+fn latches(cx: &Context, body: RefNode) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let assigned = targets(cx, body.clone());
+    for node in body {
+        let RefNode::ConditionalStatement(x) = node else {
+            continue;
+        };
+        if x.nodes.5.is_some() {
+            continue;
+        }
+        let Some(start) = unwrap_locate!(node.clone()).map(|x| x.offset) else {
+            continue;
+        };
+        let defaults: HashSet<&String> = assigned
+            .iter()
+            .filter(|x| x.0 < start)
+            .map(|x| &x.1)
+            .collect();
+        let mut latched: Vec<String> = Vec::new();
+        for (_, name) in targets(cx, node) {
+            if !defaults.contains(&name) && !latched.contains(&name) {
+                latched.push(name);
+            }
+        }
+        if latched.is_empty() {
+            continue;
+        }
+        if let Some((_, range)) = cx.ident_range(RefNode::Keyword(&x.nodes.1)) {
+            diagnostics.push(diagnostic(
+                range,
+                DiagnosticSeverity::WARNING,
+                "inferred-latch",
+                format!(
+                    "`if` without `else` infers a latch for {}",
+                    latched
+                        .iter()
+                        .map(|x| format!("`{}`", x))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+            ));
+        }
+    }
+    diagnostics
+}
+
+/// a `case` without `default` in a combinational block leaves its targets unassigned for the
+/// values it doesn't list, `unique` and `priority` cases declare the listed values complete
+fn case_default(cx: &Context, x: &CaseStatement) -> Option<Diagnostic> {
+    let (unique, keyword, default) = match x {
+        CaseStatement::Normal(x) => (
+            &x.nodes.0,
+            unwrap_locate!(RefNode::CaseKeyword(&x.nodes.1))?,
+            std::iter::once(&x.nodes.3)
+                .chain(&x.nodes.4)
+                .any(|x| matches!(x, CaseItem::Default(_))),
+        ),
+        CaseStatement::Inside(x) => (
+            &x.nodes.0,
+            unwrap_locate!(RefNode::Keyword(&x.nodes.1))?,
+            std::iter::once(&x.nodes.4)
+                .chain(&x.nodes.5)
+                .any(|x| matches!(x, CaseInsideItem::Default(_))),
+        ),
+        CaseStatement::Matches(_) => return None,
+    };
+    if unique.is_some() || default {
+        return None;
+    }
+    let (_, range) = cx.ident_range(RefNode::Locate(keyword))?;
+    Some(diagnostic(
+        range,
+        DiagnosticSeverity::WARNING,
+        "missing-case-default",
+        "`case` without `default`".to_owned(),
+    ))
+}
+
+/// the signals assigned in a statement and the offsets of their identifiers
+fn targets(cx: &Context, node: RefNode) -> Vec<(usize, String)> {
+    let mut idents: Vec<RefNode> = Vec::new();
+    for node in node {
+        match node {
+            RefNode::VariableLvalue(VariableLvalue::Identifier(x)) if x.nodes.0.is_none() => {
+                idents.extend(target(&x.nodes.1.nodes.0));
+            }
+            RefNode::DataDeclarationVariable(x) if misparsed(x) => {
+                for decl in x.nodes.4.nodes.0.contents() {
+                    if let VariableDeclAssignment::Variable(x) = decl {
+                        idents.push(RefNode::VariableIdentifier(&x.nodes.0));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    idents
+        .into_iter()
+        .filter_map(|x| Some((unwrap_locate!(x.clone())?.offset, cx.ident(x)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::check;
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let text = r#"module top(input logic clk, input logic [1:0] sel, input logic a, b);
+  logic q, r, s, t;
+  always_ff @(posedge clk) begin
+    q = a;
+    r <= b;
+  end
+  always_comb begin
+    s <= a;
+    if (sel[0]) t = b;
+  end
+  always @(a) begin
+    r = 0;
+    if (b) r = a;
+    casex (sel)
+      2'b1x: s = 1;
+    endcase
+  end
+  always_comb begin
+    unique case (sel)
+      2'b00: t = a;
+      2'b01: t = b;
+    endcase
+    priority case (sel)
+      2'b10: s = a;
+    endcase
+  end
+  always_ff @(posedge clk)
+    case (sel)
+      2'b00: q <= a;
+    endcase
+  always @* begin
+    case (sel)
+      2'b00: t = a;
+      default: t = b;
+    endcase
+    case (sel) inside
+      [0:1]: r = a;
+    endcase
+  end
+  function automatic logic pick(input logic [1:0] x);
+    case (x)
+      2'b00: return 1'b1;
+    endcase
+    return 1'b0;
+  endfunction
+endmodule
+"#;
+        let diagnostics: Vec<(String, Range)> = check(&[("/top.sv", text)], rules)
+            .into_iter()
+            .map(|x| (x.message, x.range))
+            .collect();
+        let at =
+            |line, start, end| Range::new(Position::new(line, start), Position::new(line, end));
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    "blocking assignment in `always_ff`, use `<=`".to_owned(),
+                    at(3, 4, 5)
+                ),
+                (
+                    "nonblocking assignment in `always_comb`, use `=`".to_owned(),
+                    at(7, 4, 5)
+                ),
+                (
+                    "`if` without `else` infers a latch for `t`".to_owned(),
+                    at(8, 4, 6)
+                ),
+                (
+                    "`b` is read but missing from the sensitivity list".to_owned(),
+                    at(12, 8, 9)
+                ),
+                (
+                    "`sel` is read but missing from the sensitivity list".to_owned(),
+                    at(13, 11, 14)
+                ),
+                ("`case` without `default`".to_owned(), at(13, 4, 9)),
+                (
+                    "`casex` matches x and z as wildcards, use `casez` or `case inside`".to_owned(),
+                    at(13, 4, 9)
+                ),
+                ("`case` without `default`".to_owned(), at(35, 4, 8)),
+            ]
+        );
+    }
+}
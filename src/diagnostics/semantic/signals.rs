@@ -0,0 +1,551 @@
+use super::ports::{direction, identifier};
+use super::{diagnostic, Context};
+use crate::definition::DefinitionType;
+use std::collections::{HashMap, HashSet};
+use sv_parser::*;
+use tower_lsp::lsp_types::*;
+
+/// report signals of the modules, interfaces and programs in this file that are never read,
+/// never assigned, or assigned as a whole from more than one always block, assign or instance
+pub fn signals(cx: &Context) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for node in cx.tree {
+        let interface = match node {
+            RefNode::ModuleDeclarationAnsi(_)
+            | RefNode::ModuleDeclarationNonansi(_)
+            | RefNode::ModuleDeclarationWildcard(_)
+            | RefNode::ProgramDeclarationAnsi(_)
+            | RefNode::ProgramDeclarationNonansi(_)
+            | RefNode::ProgramDeclarationWildcard(_) => false,
+            RefNode::InterfaceDeclarationAnsi(_)
+            | RefNode::InterfaceDeclarationNonansi(_)
+            | RefNode::InterfaceDeclarationWildcard(_) => true,
+            _ => continue,
+        };
+        let mut usage = Usage {
+            interface,
+            ..Usage::default()
+        };
+        usage.collect(cx, node.clone());
+        usage.reads(cx, node);
+        diagnostics.append(&mut usage.report(cx));
+    }
+    diagnostics
+}
+
+/// a net, variable or port declared in a design unit
+struct Signal {
+    name: String,
+    range: Range,
+    // the direction of ports, None for nets and variables
+    direction: Option<&'static str>,
+}
+
+/// how the signals of a design unit are used
+#[derive(Default)]
+struct Usage {
+    // the signals declared in the body of an interface are read and driven through it by the
+    // modules it is connected to
+    interface: bool,
+    signals: Vec<Signal>,
+    read: HashSet<String>,
+    written: HashSet<String>,
+    // the blocks assigning a whole signal and where, by signal
+    drivers: HashMap<String, Vec<(usize, Range)>>,
+    // the offsets of identifiers that are declared or assigned rather than read
+    not_read: HashSet<usize>,
+    // a `.*` connection to a module with unknown ports may read and assign any signal
+    opaque: bool,
+}
+
+impl Usage {
+    /// find the declarations and assignments of a module
+    fn collect(&mut self, cx: &Context, module: RefNode) {
+        // the always block, assign or instance being visited, a counter so each is distinct
+        let mut blocks: usize = 0;
+        let mut driver: Option<usize> = None;
+        // depth of constructs whose assignments don't drive a signal, ex. initial blocks, loop
+        // variables and generate branches that exclude each other
+        let mut uncounted: usize = 0;
+        // depth of constructs with declarations of their own, ex. functions
+        let mut local: usize = 0;
+        // depth of statements sv-parser mistakes for declarations
+        let mut statement: usize = 0;
+        // ANSI ports without a direction take the one of the previous port
+        let mut inherited: Option<&'static str> = None;
+        for event in module.into_iter().event() {
+            let node = match event {
+                NodeEvent::Enter(node) => node,
+                NodeEvent::Leave(node) => {
+                    match node {
+                        RefNode::ContinuousAssign(_) | RefNode::AlwaysConstruct(_) => driver = None,
+                        RefNode::InitialConstruct(_)
+                        | RefNode::FinalConstruct(_)
+                        | RefNode::ForInitialization(_)
+                        | RefNode::ForStep(_)
+                        | RefNode::IfGenerateConstruct(_)
+                        | RefNode::CaseGenerateConstruct(_) => uncounted -= 1,
+                        RefNode::FunctionDeclaration(_)
+                        | RefNode::TaskDeclaration(_)
+                        | RefNode::ClassDeclaration(_)
+                        | RefNode::StructUnionMember(_) => {
+                            uncounted -= 1;
+                            local -= 1;
+                        }
+                        RefNode::DataDeclarationVariable(x) if misparsed(x) => statement -= 1,
+                        _ => (),
+                    }
+                    continue;
+                }
+            };
+            let counted = |driver: Option<usize>| driver.filter(|_| uncounted == 0);
+            match node {
+                RefNode::ContinuousAssign(_) | RefNode::AlwaysConstruct(_) => {
+                    blocks += 1;
+                    driver = Some(blocks);
+                }
+                RefNode::InitialConstruct(_)
+                | RefNode::FinalConstruct(_)
+                | RefNode::ForInitialization(_)
+                | RefNode::ForStep(_)
+                | RefNode::IfGenerateConstruct(_)
+                | RefNode::CaseGenerateConstruct(_) => uncounted += 1,
+                RefNode::FunctionDeclaration(_)
+                | RefNode::TaskDeclaration(_)
+                | RefNode::ClassDeclaration(_)
+                | RefNode::StructUnionMember(_) => {
+                    uncounted += 1;
+                    local += 1;
+                }
+                RefNode::NetDeclAssignment(x) if local == 0 => {
+                    let ident = RefNode::NetIdentifier(&x.nodes.0);
+                    self.declare(cx, ident.clone(), None);
+                    if x.nodes.2.is_some() {
+                        blocks += 1;
+                        self.assign(cx, ident, counted(Some(blocks)));
+                    }
+                }
+                RefNode::DataDeclarationVariable(x) if misparsed(x) => statement += 1,
+                RefNode::VariableDeclAssignment(VariableDeclAssignment::Variable(x))
+                    if statement > 0 =>
+                {
+                    let whole = x.nodes.1.is_empty();
+                    let ident = RefNode::VariableIdentifier(&x.nodes.0);
+                    self.assign(cx, ident, counted(driver.filter(|_| whole)));
+                }
+                RefNode::VariableDeclAssignment(VariableDeclAssignment::Variable(x))
+                    if local == 0 =>
+                {
+                    let ident = RefNode::VariableIdentifier(&x.nodes.0);
+                    self.declare(cx, ident.clone(), None);
+                    if x.nodes.2.is_some() {
+                        self.assign(cx, ident, None);
+                    }
+                }
+                RefNode::AnsiPortDeclaration(AnsiPortDeclaration::Net(x)) => {
+                    let dir = match &x.nodes.0 {
+                        Some(NetPortHeaderOrInterfacePortHeader::NetPortHeader(x)) => {
+                            x.nodes.0.as_ref().map(port_direction).or(inherited)
+                        }
+                        Some(NetPortHeaderOrInterfacePortHeader::InterfacePortHeader(_)) => {
+                            Some("interface")
+                        }
+                        None => inherited,
+                    };
+                    inherited = dir;
+                    if dir != Some("interface") {
+                        self.declare(
+                            cx,
+                            RefNode::PortIdentifier(&x.nodes.1),
+                            dir.or(Some("inout")),
+                        );
+                    }
+                }
+                RefNode::AnsiPortDeclaration(AnsiPortDeclaration::Variable(x)) => {
+                    let dir = x
+                        .nodes
+                        .0
+                        .as_ref()
+                        .and_then(|x| x.nodes.0.as_ref())
+                        .map(port_direction)
+                        .or(inherited);
+                    inherited = dir;
+                    if dir != Some("interface") {
+                        self.declare(
+                            cx,
+                            RefNode::PortIdentifier(&x.nodes.1),
+                            dir.or(Some("inout")),
+                        );
+                    }
+                }
+                RefNode::InputDeclaration(_) => self.declare_ports(cx, node, "input"),
+                RefNode::OutputDeclaration(_) => self.declare_ports(cx, node, "output"),
+                RefNode::InoutDeclaration(_) => self.declare_ports(cx, node, "inout"),
+                // the port list of a non-ANSI module names the ports declared in its body
+                RefNode::PortReference(x) => {
+                    if let Some(loc) = unwrap_locate!(RefNode::PortIdentifier(&x.nodes.0)) {
+                        self.not_read.insert(loc.offset);
+                    }
+                }
+                RefNode::NetLvalue(NetLvalue::Identifier(x)) => {
+                    let ident = match &x.nodes.0 {
+                        PsOrHierarchicalNetIdentifier::PackageScope(x) if x.nodes.0.is_none() => {
+                            Some(RefNode::NetIdentifier(&x.nodes.1))
+                        }
+                        PsOrHierarchicalNetIdentifier::HierarchicalNetIdentifier(x) => {
+                            target(&x.nodes.0)
+                        }
+                        _ => None,
+                    };
+                    if let Some(ident) = ident {
+                        let whole = unwrap_locate!(RefNode::ConstantSelect(&x.nodes.1)).is_none();
+                        self.assign(cx, ident, counted(driver.filter(|_| whole)));
+                    }
+                }
+                RefNode::VariableLvalue(VariableLvalue::Identifier(x)) if x.nodes.0.is_none() => {
+                    if let Some(ident) = target(&x.nodes.1.nodes.0) {
+                        let whole = unwrap_locate!(RefNode::Select(&x.nodes.2)).is_none();
+                        self.assign(cx, ident, counted(driver.filter(|_| whole)));
+                    }
+                }
+                RefNode::ModuleInstantiation(x) if local == 0 => {
+                    for instance in x.nodes.2.contents() {
+                        blocks += 1;
+                        self.instance(cx, &x.nodes.0, instance, counted(Some(blocks)));
+                    }
+                }
+                // $readmemh and $readmemb fill a memory
+                RefNode::SystemTfCall(_) => {
+                    let readmem = unwrap_locate!(node.clone())
+                        .and_then(|x| cx.tree.get_str(x))
+                        .is_some_and(|x| x.starts_with("$readmem"));
+                    if readmem {
+                        for ident in node.clone() {
+                            if let RefNode::Identifier(_) = ident {
+                                self.written.extend(cx.ident(ident));
+                            }
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// every identifier that isn't declared or assigned is read
+    fn reads(&mut self, cx: &Context, module: RefNode) {
+        for node in module {
+            if let RefNode::Identifier(_) = node {
+                let Some(loc) = unwrap_locate!(node.clone()) else {
+                    continue;
+                };
+                if !self.not_read.contains(&loc.offset) {
+                    self.read.extend(cx.ident(node));
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, cx: &Context, ident: RefNode, direction: Option<&'static str>) {
+        if let Some(loc) = unwrap_locate!(ident.clone()) {
+            self.not_read.insert(loc.offset);
+        }
+        let Some((name, range)) = cx.ident_range(ident) else {
+            return;
+        };
+        // a port of a non-ANSI module is declared again with its type
+        match self.signals.iter_mut().find(|x| x.name == name) {
+            Some(signal) => signal.direction = signal.direction.or(direction),
+            None => self.signals.push(Signal {
+                name,
+                range,
+                direction,
+            }),
+        }
+    }
+
+    fn declare_ports(&mut self, cx: &Context, decl: RefNode, direction: &'static str) {
+        for node in decl {
+            if let RefNode::PortIdentifier(_) | RefNode::VariableIdentifier(_) = node {
+                self.declare(cx, node, Some(direction));
+            }
+        }
+    }
+
+    /// an assignment to a signal, by the always block, assign or instance driving it if it
+    /// assigns the whole signal
+    fn assign(&mut self, cx: &Context, ident: RefNode, driver: Option<usize>) {
+        if let Some(loc) = unwrap_locate!(ident.clone()) {
+            self.not_read.insert(loc.offset);
+        }
+        let Some(name) = cx.ident(ident.clone()) else {
+            return;
+        };
+        if let (Some(driver), Some((_, range))) = (driver, cx.ident_range(ident)) {
+            self.drivers
+                .entry(name.clone())
+                .or_default()
+                .push((driver, range));
+        }
+        self.written.insert(name);
+    }
+
+    /// the signals read and assigned by the port connections of an instance
+    fn instance(
+        &mut self,
+        cx: &Context,
+        unit: &ModuleIdentifier,
+        instance: &HierarchicalInstance,
+        driver: Option<usize>,
+    ) {
+        let ports: Vec<(String, Option<&str>)> = cx
+            .ident(RefNode::ModuleIdentifier(unit))
+            .and_then(|x| cx.design_unit(&x))
+            .map(|unit| {
+                unit.defs()
+                    .iter()
+                    .filter(|x| matches!(x.def_type(), DefinitionType::Port))
+                    .map(|x| (x.ident(), direction(x.as_ref())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        match &instance.nodes.1.nodes.1 {
+            Some(ListOfPortConnections::Ordered(x)) => {
+                for (i, connection) in x.nodes.0.contents().into_iter().enumerate() {
+                    if let Some(expr) = &connection.nodes.1 {
+                        let dir = ports.get(i).and_then(|x| x.1);
+                        self.connect(cx, expr, dir, driver);
+                    }
+                }
+            }
+            Some(ListOfPortConnections::Named(x)) => {
+                let mut connected: HashSet<String> = HashSet::new();
+                let mut wildcard = false;
+                for connection in x.nodes.0.contents() {
+                    let connection = match connection {
+                        NamedPortConnection::Identifier(x) => x,
+                        NamedPortConnection::Asterisk(_) => {
+                            wildcard = true;
+                            continue;
+                        }
+                    };
+                    let port = RefNode::PortIdentifier(&connection.nodes.2);
+                    if let Some(loc) = unwrap_locate!(port.clone()) {
+                        self.not_read.insert(loc.offset);
+                    }
+                    let Some(name) = cx.ident(port) else {
+                        continue;
+                    };
+                    let dir = ports.iter().find(|x| x.0 == name).and_then(|x| x.1);
+                    match &connection.nodes.3 {
+                        Some(x) => {
+                            if let Some(expr) = &x.nodes.1 {
+                                self.connect(cx, expr, dir, driver);
+                            }
+                        }
+                        // `.clk` connects the signal of the same name
+                        None => self.connect_name(&name, dir),
+                    }
+                    connected.insert(name);
+                }
+                if wildcard {
+                    self.opaque |= ports.is_empty();
+                    for (name, dir) in &ports {
+                        if !connected.contains(name) {
+                            self.connect_name(name, *dir);
+                        }
+                    }
+                }
+            }
+            None => (),
+        }
+    }
+
+    /// a signal connected to a port, inputs are left to the reads
+    fn connect(
+        &mut self,
+        cx: &Context,
+        expr: &Expression,
+        dir: Option<&str>,
+        driver: Option<usize>,
+    ) {
+        let Some((ident, select)) = identifier(expr) else {
+            return;
+        };
+        match dir {
+            Some("input") => (),
+            Some("output") => {
+                let whole = unwrap_locate!(RefNode::Select(select)).is_none();
+                self.assign(cx, RefNode::Identifier(ident), driver.filter(|_| whole));
+            }
+            _ => self.written.extend(cx.ident(RefNode::Identifier(ident))),
+        }
+    }
+
+    fn connect_name(&mut self, name: &str, dir: Option<&str>) {
+        if dir != Some("output") {
+            self.read.insert(name.to_owned());
+        }
+        if dir != Some("input") {
+            self.written.insert(name.to_owned());
+        }
+    }
+
+    fn report(&self, cx: &Context) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        for signal in &self.signals {
+            // the other side of a port reads outputs and drives inputs
+            let shared = self.interface && signal.direction.is_none();
+            let read = self.opaque
+                || shared
+                || self.read.contains(&signal.name)
+                || matches!(signal.direction, Some("output" | "inout" | "ref"));
+            let written = self.opaque
+                || shared
+                || self.written.contains(&signal.name)
+                || matches!(signal.direction, Some("input" | "inout" | "ref"));
+            if !read {
+                let message = if written {
+                    format!("`{}` is never read", signal.name)
+                } else {
+                    format!("`{}` is never used", signal.name)
+                };
+                let mut diag = diagnostic(
+                    signal.range,
+                    DiagnosticSeverity::HINT,
+                    "unused-signal",
+                    message,
+                );
+                diag.tags = Some(vec![DiagnosticTag::UNNECESSARY]);
+                diagnostics.push(diag);
+            } else if !written {
+                diagnostics.push(diagnostic(
+                    signal.range,
+                    DiagnosticSeverity::WARNING,
+                    "undriven-signal",
+                    format!("`{}` is never assigned", signal.name),
+                ));
+            }
+            let Some(drivers) = self.drivers.get(&signal.name) else {
+                continue;
+            };
+            let blocks: HashSet<usize> = drivers.iter().map(|x| x.0).collect();
+            if blocks.len() > 1 {
+                let mut diag = diagnostic(
+                    signal.range,
+                    DiagnosticSeverity::WARNING,
+                    "multi-driven-signal",
+                    format!("`{}` has {} drivers", signal.name, blocks.len()),
+                );
+                diag.related_information = Some(
+                    drivers
+                        .iter()
+                        .map(|x| DiagnosticRelatedInformation {
+                            location: Location::new(cx.uri.clone(), x.1),
+                            message: format!("`{}` is driven here", signal.name),
+                        })
+                        .collect(),
+                );
+                diagnostics.push(diag);
+            }
+        }
+        diagnostics
+    }
+}
+
+/// the signal an assignment to a hierarchical name assigns, ex. `s` for `s.field = 1`
+pub(super) fn target(ident: &HierarchicalIdentifier) -> Option<RefNode<'_>> {
+    let (root, path, ident) = &ident.nodes;
+    if root.is_some() {
+        return None;
+    }
+    Some(RefNode::Identifier(path.first().map_or(ident, |x| &x.0)))
+}
+
+/// sv-parser reads the first statement of a block, ex. `a = b;`, as the declaration of a
+/// variable with an implicit type
+pub(super) fn misparsed(x: &DataDeclarationVariable) -> bool {
+    let (constant, var, lifetime, data_type, _, _) = &x.nodes;
+    constant.is_none()
+        && var.is_none()
+        && lifetime.is_none()
+        && unwrap_locate!(RefNode::DataTypeOrImplicit(data_type)).is_none()
+}
+
+fn port_direction(dir: &PortDirection) -> &'static str {
+    match dir {
+        PortDirection::Input(_) => "input",
+        PortDirection::Output(_) => "output",
+        PortDirection::Inout(_) => "inout",
+        PortDirection::Ref(_) => "ref",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::check;
+    use super::*;
+
+    #[test]
+    fn test_signals() {
+        let lib = r#"module fifo(input logic clk, output logic [7:0] dout);
+endmodule
+"#;
+        let text = r#"module top(input logic clk, rst, input logic [7:0] unused_in, output logic [7:0] q);
+  logic [7:0] a, b, c, d, mem [4];
+  wire w = a;
+  integer i;
+  always_ff @(posedge clk) begin
+    for (i = 0; i < 4; i++) mem[i] <= a;
+    q <= b[0] ? mem[0] : d;
+  end
+  always_comb begin
+    a = c;
+    b = 0;
+  end
+  assign b = rst;
+  fifo u_fifo(.clk, .dout(d));
+  initial $readmemh("mem.hex", mem);
+endmodule
+module legacy(x, y);
+  input x;
+  output y;
+  reg y;
+endmodule
+interface bus(input logic clk, input logic rst);
+  logic valid, ready;
+  logic [7:0] data;
+  assign ready = 1;
+  always_ff @(posedge clk) valid <= ready;
+  assign valid = 0;
+  modport src(output valid, data, input ready);
+endinterface
+program check(input logic clk, input logic done);
+  logic seen;
+  initial @(posedge clk) $display("tick");
+endprogram
+"#;
+        let diagnostics: Vec<(String, Range)> =
+            check(&[("/lib.sv", lib), ("/top.sv", text)], signals)
+                .into_iter()
+                .map(|x| (x.message, x.range))
+                .collect();
+        let at =
+            |line, start, end| Range::new(Position::new(line, start), Position::new(line, end));
+        assert_eq!(
+            diagnostics,
+            vec![
+                ("`unused_in` is never read".to_owned(), at(0, 51, 60)),
+                ("`b` has 2 drivers".to_owned(), at(1, 17, 18)),
+                ("`c` is never assigned".to_owned(), at(1, 20, 21)),
+                ("`w` is never read".to_owned(), at(2, 7, 8)),
+                ("`x` is never read".to_owned(), at(17, 8, 9)),
+                ("`y` is never assigned".to_owned(), at(18, 9, 10)),
+                ("`rst` is never read".to_owned(), at(21, 43, 46)),
+                ("`valid` has 2 drivers".to_owned(), at(22, 8, 13)),
+                ("`done` is never read".to_owned(), at(29, 43, 47)),
+                ("`seen` is never used".to_owned(), at(30, 8, 12)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use super::{diagnostic, Context};
+use sv_parser::*;
+use tower_lsp::lsp_types::*;
+
+/// report instances of unknown modules, imports of unknown packages and ports of unknown
+/// interfaces
+pub fn unresolved(cx: &Context) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let Some(workspace) = cx.workspace else {
+        return diagnostics;
+    };
+    for node in cx.tree {
+        let Some((ident, code, kind)) = used_name(node) else {
+            continue;
+        };
+        let Some((name, range)) = cx.ident_range(ident) else {
+            continue;
+        };
+        let known = match kind {
+            "module" => workspace.names.modules.contains(&name),
+            "package" => workspace.names.packages.contains(&name),
+            _ => {
+                workspace.names.interfaces.contains(&name) || workspace.names.others.contains(&name)
+            }
+        };
+        if !known {
+            diagnostics.push(diagnostic(
+                range,
+                DiagnosticSeverity::ERROR,
+                code,
+                format!("unknown {} `{}`", kind, name),
+            ));
+        }
+    }
+    diagnostics
+}
+
+/// the identifier of a module, interface or program instantiated, a package imported or an
+/// interface used as a port type, with the code and kind reported if it is unknown
+pub(super) fn used_name(node: RefNode) -> Option<(RefNode, &'static str, &'static str)> {
+    Some(match node {
+        RefNode::ModuleInstantiation(x) => (
+            RefNode::ModuleIdentifier(&x.nodes.0),
+            "unresolved-module",
+            "module",
+        ),
+        RefNode::InterfaceInstantiation(x) => (
+            RefNode::InterfaceIdentifier(&x.nodes.0),
+            "unresolved-module",
+            "module",
+        ),
+        RefNode::ProgramInstantiation(x) => (
+            RefNode::ProgramIdentifier(&x.nodes.0),
+            "unresolved-module",
+            "module",
+        ),
+        RefNode::PackageImportItem(PackageImportItem::Identifier(x)) => (
+            RefNode::PackageIdentifier(&x.nodes.0),
+            "unresolved-package",
+            "package",
+        ),
+        RefNode::PackageImportItem(PackageImportItem::Asterisk(x)) => (
+            RefNode::PackageIdentifier(&x.nodes.0),
+            "unresolved-package",
+            "package",
+        ),
+        RefNode::InterfacePortHeader(InterfacePortHeader::Identifier(x)) => (
+            RefNode::InterfaceIdentifier(&x.nodes.0),
+            "unresolved-interface",
+            "interface",
+        ),
+        RefNode::InterfacePortDeclaration(x) => (
+            RefNode::InterfaceIdentifier(&x.nodes.0),
+            "unresolved-interface",
+            "interface",
+        ),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{check, WorkspaceNames};
+    use super::*;
+
+    #[test]
+    fn test_unresolved() {
+        let lib = r#"package pkg;
+  typedef logic [7:0] byte_t;
+endpackage
+interface bus_if;
+  logic valid;
+  modport master(output valid);
+endinterface
+module leaf(input logic a);
+endmodule
+"#;
+        let text = r#"module top
+  import pkg::*;
+  import missing_pkg::*;
+(
+  bus_if.master bus,
+  other_if port,
+  input byte_t data
+);
+  leaf u_leaf(.a(1'b0));
+  missing u_missing();
+  bus_if u_bus();
+endmodule
+"#;
+        let diagnostics: Vec<(String, Range)> =
+            check(&[("/lib.sv", lib), ("/top.sv", text)], unresolved)
+                .into_iter()
+                .map(|x| (x.message, x.range))
+                .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    "unknown package `missing_pkg`".to_owned(),
+                    Range::new(Position::new(2, 9), Position::new(2, 20))
+                ),
+                (
+                    "unknown interface `other_if`".to_owned(),
+                    Range::new(Position::new(5, 2), Position::new(5, 10))
+                ),
+                (
+                    "unknown module `missing`".to_owned(),
+                    Range::new(Position::new(9, 2), Position::new(9, 9))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unparsed_names() {
+        let mut workspace = WorkspaceNames::default();
+        workspace.add_text(
+            r#"module automatic adder #(parameter W = 8) (
+interface class shape;
+endclass
+package util;
+typedef struct packed {
+  logic a;
+} pair_t;
+typedef logic [3:0] nibble_t;
+"#,
+        );
+        assert!(workspace.modules.contains("adder"));
+        assert!(!workspace.modules.contains("class"));
+        assert!(workspace.packages.contains("util"));
+        assert!(workspace.others.contains("pair_t"));
+        assert!(workspace.others.contains("nibble_t"));
+    }
+}
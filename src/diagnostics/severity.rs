@@ -0,0 +1,157 @@
+use crate::server::{DiagnosticsConfig, RuleSeverity};
+use globset::Glob;
+use log::error;
+use std::collections::HashMap;
+use std::env::current_dir;
+use std::path::Path;
+use tower_lsp::lsp_types::*;
+
+/// the configured severity for a diagnostic, by rule code, then source, then "*"
+fn rule_severity(
+    rules: &HashMap<String, RuleSeverity>,
+    diagnostic: &Diagnostic,
+) -> Option<RuleSeverity> {
+    let code = match &diagnostic.code {
+        Some(NumberOrString::String(code)) => Some(code.clone()),
+        Some(NumberOrString::Number(code)) => Some(code.to_string()),
+        None => None,
+    };
+    let lookup = |key: &str| {
+        rules
+            .iter()
+            .find(|(rule, _)| rule.eq_ignore_ascii_case(key))
+            .map(|(_, severity)| *severity)
+    };
+    code.and_then(|x| lookup(&x))
+        .or_else(|| diagnostic.source.as_ref().and_then(|x| lookup(x)))
+        .or_else(|| rules.get("*").copied())
+}
+
+/// whether a file matches an override glob, relative globs are matched against the path
+/// relative to the working directory
+fn glob_matches(glob: &str, path: &Path) -> bool {
+    let matcher = match Glob::new(glob) {
+        Ok(glob) => glob.compile_matcher(),
+        Err(e) => {
+            error!("invalid glob {}: {}", glob, e);
+            return false;
+        }
+    };
+    if matcher.is_match(path) {
+        return true;
+    }
+    current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok().map(|x| matcher.is_match(x)))
+        .unwrap_or(false)
+}
+
+/// remap the severity of diagnostics reported for a file and drop the ones turned off
+pub fn apply_severity(
+    path: Option<&Path>,
+    diagnostics: Vec<Diagnostic>,
+    conf: &DiagnosticsConfig,
+) -> Vec<Diagnostic> {
+    if conf.rules.is_empty() && conf.overrides.is_empty() {
+        return diagnostics;
+    }
+    // most specific rules first
+    let mut rules: Vec<&HashMap<String, RuleSeverity>> = match path {
+        Some(path) => conf
+            .overrides
+            .iter()
+            .rev()
+            .filter(|x| glob_matches(&x.glob, path))
+            .map(|x| &x.rules)
+            .collect(),
+        None => Vec::new(),
+    };
+    rules.push(&conf.rules);
+    diagnostics
+        .into_iter()
+        .filter_map(|mut diagnostic| {
+            let severity = rules.iter().find_map(|x| rule_severity(x, &diagnostic));
+            diagnostic.severity = match severity {
+                Some(RuleSeverity::Off) => return None,
+                Some(RuleSeverity::Error) => Some(DiagnosticSeverity::ERROR),
+                Some(RuleSeverity::Warning) => Some(DiagnosticSeverity::WARNING),
+                Some(RuleSeverity::Info) => Some(DiagnosticSeverity::INFORMATION),
+                Some(RuleSeverity::Hint) => Some(DiagnosticSeverity::HINT),
+                None => diagnostic.severity,
+            };
+            Some(diagnostic)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{DiagnosticsOverride, ProjectConfig};
+
+    fn diagnostic(code: Option<&str>, source: &str) -> Diagnostic {
+        Diagnostic {
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: code.map(|x| NumberOrString::String(x.to_owned())),
+            source: Some(source.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_config() {
+        let config = r#"
+diagnostics:
+  rules:
+    WIDTH: error
+    UNUSED: Off
+  overrides:
+    - glob: "third_party/**"
+      rules:
+        "*": off
+"#;
+        let config = serde_yaml::from_str::<ProjectConfig>(config).unwrap();
+        assert_eq!(config.diagnostics.rules["WIDTH"], RuleSeverity::Error);
+        assert_eq!(config.diagnostics.rules["UNUSED"], RuleSeverity::Off);
+        assert_eq!(
+            config.diagnostics.overrides[0].rules["*"],
+            RuleSeverity::Off
+        );
+    }
+
+    #[test]
+    fn test_apply_severity() {
+        let conf = DiagnosticsConfig {
+            rules: HashMap::from([
+                ("width".to_owned(), RuleSeverity::Error),
+                ("UNUSED".to_owned(), RuleSeverity::Off),
+                ("verible".to_owned(), RuleSeverity::Hint),
+            ]),
+            overrides: vec![DiagnosticsOverride {
+                glob: "/ip/**/*.sv".to_owned(),
+                rules: HashMap::from([
+                    ("*".to_owned(), RuleSeverity::Off),
+                    ("WIDTH".to_owned(), RuleSeverity::Info),
+                ]),
+            }],
+        };
+        let diagnostics = vec![
+            diagnostic(Some("WIDTH"), "verilator"),
+            diagnostic(Some("UNUSED"), "verilator"),
+            diagnostic(Some("CASEINCOMPLETE"), "verilator"),
+            diagnostic(None, "verible"),
+        ];
+        let result = apply_severity(Some(Path::new("/src/top.sv")), diagnostics.clone(), &conf);
+        assert_eq!(
+            result.iter().map(|x| x.severity).collect::<Vec<_>>(),
+            vec![
+                Some(DiagnosticSeverity::ERROR),
+                Some(DiagnosticSeverity::WARNING),
+                Some(DiagnosticSeverity::HINT),
+            ]
+        );
+        let result = apply_severity(Some(Path::new("/ip/uart/uart.sv")), diagnostics, &conf);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].severity, Some(DiagnosticSeverity::INFORMATION));
+    }
+}
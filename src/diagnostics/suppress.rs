@@ -0,0 +1,203 @@
+use regex::Regex;
+use ropey::Rope;
+use std::ops::Range as StdRange;
+use tower_lsp::lsp_types::*;
+
+/// lines on which diagnostics matching the codes are suppressed, no codes suppresses everything
+#[derive(Debug, PartialEq)]
+struct Suppression {
+    lines: StdRange<u32>,
+    codes: Vec<String>,
+}
+
+impl Suppression {
+    fn matches(&self, diagnostic: &Diagnostic) -> bool {
+        if !self.lines.contains(&diagnostic.range.start.line) {
+            return false;
+        }
+        self.codes.is_empty()
+            || self.codes.iter().any(|x| {
+                let code = match &diagnostic.code {
+                    Some(NumberOrString::String(code)) => code.eq_ignore_ascii_case(x),
+                    Some(NumberOrString::Number(code)) => &code.to_string() == x,
+                    None => false,
+                };
+                code || diagnostic
+                    .source
+                    .as_ref()
+                    .is_some_and(|source| source.eq_ignore_ascii_case(x))
+            })
+    }
+}
+
+/// find suppression comments in a file:
+/// `// veridian-disable-next-line <codes>`, `// veridian-disable <codes>` ...
+/// `// veridian-enable <codes>` and `// veridian-disable-file <codes>`
+fn suppressions(rope: &Rope) -> Vec<Suppression> {
+    static RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(
+            r"//\s*veridian-(?P<kind>disable-next-line|disable-file|disable|enable)\b(?P<codes>.*)",
+        )
+        .unwrap()
+    });
+    let mut result: Vec<Suppression> = Vec::new();
+    // regions opened by veridian-disable, with their starting line
+    let mut open: Vec<(u32, Vec<String>)> = Vec::new();
+    for (i, line) in rope.lines().enumerate() {
+        let line_num = i as u32;
+        let text = line.to_string();
+        let caps = match re.captures(&text) {
+            Some(caps) => caps,
+            None => continue,
+        };
+        let codes: Vec<String> = caps
+            .name("codes")
+            .map_or("", |x| x.as_str())
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_string())
+            .collect();
+        match caps.name("kind").map(|x| x.as_str()) {
+            Some("disable-next-line") => result.push(Suppression {
+                lines: line_num + 1..line_num + 2,
+                codes,
+            }),
+            Some("disable-file") => result.push(Suppression {
+                lines: 0..u32::MAX,
+                codes,
+            }),
+            Some("disable") => open.push((line_num, codes)),
+            Some("enable") => {
+                let mut still_open: Vec<(u32, Vec<String>)> = Vec::new();
+                for (start, region_codes) in open {
+                    if codes.is_empty() {
+                        result.push(Suppression {
+                            lines: start..line_num + 1,
+                            codes: region_codes,
+                        });
+                        continue;
+                    }
+                    // only the listed codes are enabled again
+                    let (closed, remaining): (Vec<String>, Vec<String>) = region_codes
+                        .into_iter()
+                        .partition(|x| codes.iter().any(|y| y.eq_ignore_ascii_case(x)));
+                    if !closed.is_empty() {
+                        result.push(Suppression {
+                            lines: start..line_num + 1,
+                            codes: closed,
+                        });
+                    }
+                    if !remaining.is_empty() {
+                        still_open.push((start, remaining));
+                    }
+                }
+                open = still_open;
+            }
+            _ => (),
+        }
+    }
+    for (start, codes) in open {
+        result.push(Suppression {
+            lines: start..u32::MAX,
+            codes,
+        });
+    }
+    result
+}
+
+/// remove diagnostics waived by suppression comments in the file they are reported in
+pub fn filter_suppressed(rope: &Rope, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    let suppressions = suppressions(rope);
+    if suppressions.is_empty() {
+        return diagnostics;
+    }
+    diagnostics
+        .into_iter()
+        .filter(|x| !suppressions.iter().any(|y| y.matches(x)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(line: u32, code: Option<&str>, source: &str) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(line, 0), Position::new(line, 1)),
+            code: code.map(|x| NumberOrString::String(x.to_owned())),
+            source: Some(source.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_suppressions() {
+        let text = r#"// veridian-disable-file DECLFILENAME
+module test;
+  // veridian-disable-next-line WIDTH, UNUSED
+  assign a = b;
+  // veridian-disable verible
+  logic c;
+  // veridian-disable UNDRIVEN
+  logic d;
+  // veridian-enable verible
+  logic e;
+  // veridian-enable
+endmodule
+"#;
+        assert_eq!(
+            suppressions(&Rope::from_str(text)),
+            vec![
+                Suppression {
+                    lines: 0..u32::MAX,
+                    codes: vec!["DECLFILENAME".to_owned()],
+                },
+                Suppression {
+                    lines: 3..4,
+                    codes: vec!["WIDTH".to_owned(), "UNUSED".to_owned()],
+                },
+                Suppression {
+                    lines: 4..9,
+                    codes: vec!["verible".to_owned()],
+                },
+                Suppression {
+                    lines: 6..11,
+                    codes: vec!["UNDRIVEN".to_owned()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_suppressed() {
+        let text = r#"module test;
+  // veridian-disable-next-line width
+  assign a = b;
+  // veridian-disable
+  logic c;
+  // veridian-enable
+  logic d;
+endmodule
+"#;
+        let diagnostics = vec![
+            diagnostic(0, Some("DECLFILENAME"), "verilator"),
+            diagnostic(2, Some("WIDTH"), "verilator"),
+            diagnostic(2, Some("UNUSED"), "verilator"),
+            diagnostic(4, None, "verible"),
+            diagnostic(6, None, "verible"),
+        ];
+        let filtered = filter_suppressed(&Rope::from_str(text), diagnostics);
+        assert_eq!(
+            filtered
+                .iter()
+                .map(|x| x.range.start.line)
+                .collect::<Vec<u32>>(),
+            vec![0, 2, 6]
+        );
+        assert_eq!(
+            filtered[1].code,
+            Some(NumberOrString::String("UNUSED".to_owned()))
+        );
+    }
+}
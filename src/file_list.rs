@@ -0,0 +1,387 @@
+use log::warn;
+use path_clean::PathClean;
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+use std::env::current_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// options taking an argument that doesn't concern the language server
+const SKIPPED_ARGS: &[&str] = &[
+    // verilator
+    "--Mdir",
+    "--prefix",
+    "--mod-prefix",
+    "--top",
+    "--top-module",
+    "-j",
+    "--build-jobs",
+    "--threads",
+    "--x-assign",
+    "--x-initial",
+    "--timescale",
+    "--timescale-override",
+    "--default-language",
+    "--language",
+    "--output-split",
+    "--output-split-cfuncs",
+    "--output-split-ctrace",
+    "--unroll-count",
+    "--unroll-stmts",
+    "--inline-mult",
+    "--reloop-limit",
+    "--converge-limit",
+    "--max-num-width",
+    "--trace-depth",
+    "--trace-max-array",
+    "--trace-max-width",
+    "--compiler",
+    "--hierarchical-block",
+    "--protect-key",
+    "--protect-lib",
+    "--l2-name",
+    "--pins-bv",
+    "--xml-output",
+    "--waiver-output",
+    "-MAKEFLAGS",
+    "--MAKEFLAGS",
+    "-CFLAGS",
+    "-LDFLAGS",
+    // VCS
+    "-o",
+    "-l",
+    "-top",
+    "-timescale",
+    "-Mdir",
+    "-P",
+    "-assert",
+    "-ignore",
+    "-cm",
+    "-cm_dir",
+    "-cm_name",
+    "-cm_hier",
+    "-ntb_opts",
+];
+
+/// the sources, include dirs and defines of a verilator/VCS style file list (.f)
+#[derive(Debug, Default, PartialEq)]
+pub struct FileList {
+    // source files, plain paths and -v library files
+    pub files: Vec<PathBuf>,
+    // +incdir+ and -I directories
+    pub include_dirs: Vec<PathBuf>,
+    // -y directories, searched for sources with a library extension
+    pub lib_dirs: Vec<PathBuf>,
+    // +libext+ extensions, with the leading dot
+    pub libext: Vec<String>,
+    // +define+ and -D macros, a macro without a value is None
+    pub defines: HashMap<String, Option<String>>,
+}
+
+impl FileList {
+    /// read a file list and the lists nested in it, relative paths are relative to the working
+    /// directory except in lists nested with -F, where they are relative to the list
+    pub fn read(path: &Path) -> Option<FileList> {
+        FileList::read_with_env(path, &|name| std::env::var(name).ok())
+    }
+
+    /// read a file list, looking up the environment variables it uses with env
+    fn read_with_env(path: &Path, env: &dyn Fn(&str) -> Option<String>) -> Option<FileList> {
+        let mut list = FileList::default();
+        let mut visited: HashSet<PathBuf> = HashSet::new();
+        list.read_nested(path, &current_dir().ok()?, false, &mut visited, env)
+            .then_some(list)
+    }
+
+    /// the listed sources followed by the sources found in the library directories
+    pub fn sources(&self) -> Vec<PathBuf> {
+        let libext: Vec<&str> = if self.libext.is_empty() {
+            vec![".v", ".sv"]
+        } else {
+            self.libext.iter().map(String::as_str).collect()
+        };
+        let mut sources = self.files.clone();
+        for dir in &self.lib_dirs {
+            let Ok(entries) = fs::read_dir(dir) else {
+                warn!("couldn't read library directory {}", dir.display());
+                continue;
+            };
+            let mut found: Vec<PathBuf> = entries
+                .flatten()
+                .map(|x| x.path())
+                .filter(|x| {
+                    x.is_file()
+                        && x.file_name()
+                            .and_then(|x| x.to_str())
+                            .is_some_and(|x| libext.iter().any(|ext| x.ends_with(ext)))
+                })
+                .collect();
+            found.sort();
+            for path in found {
+                if !sources.contains(&path) {
+                    sources.push(path);
+                }
+            }
+        }
+        sources
+    }
+
+    /// read a list, its relative paths are relative to base, or to the list if relative_to_list
+    fn read_nested(
+        &mut self,
+        path: &Path,
+        base: &Path,
+        relative_to_list: bool,
+        visited: &mut HashSet<PathBuf>,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> bool {
+        let path = base.join(path).clean();
+        if !visited.insert(path.clone()) {
+            warn!("file list {} is nested in itself", path.display());
+            return true;
+        }
+        let Ok(text) = fs::read_to_string(&path) else {
+            warn!("couldn't read file list {}", path.display());
+            return false;
+        };
+        let base = match path.parent() {
+            Some(dir) if relative_to_list => dir.to_path_buf(),
+            _ => base.to_path_buf(),
+        };
+        let mut tokens = tokens(&text).into_iter();
+        while let Some(token) = tokens.next() {
+            let token = expand_env(&token, env);
+            if let Some(dirs) = token.strip_prefix("+incdir+") {
+                self.include_dirs
+                    .extend(plus_args(dirs).map(|x| base.join(x).clean()));
+            } else if let Some(defines) = token.strip_prefix("+define+") {
+                for define in plus_args(defines) {
+                    self.define(define);
+                }
+            } else if let Some(exts) = token.strip_prefix("+libext+") {
+                self.libext.extend(plus_args(exts).map(str::to_owned));
+            } else if let Some(dir) = token.strip_prefix("-I").filter(|x| !x.is_empty()) {
+                self.include_dirs.push(base.join(dir).clean());
+            } else if let Some(define) = token.strip_prefix("-D").filter(|x| !x.is_empty()) {
+                self.define(define);
+            } else if matches!(token.as_str(), "-I" | "-D" | "-y" | "-v" | "-f" | "-F") {
+                let Some(arg) = tokens.next().map(|x| expand_env(&x, env)) else {
+                    warn!("{} in {} is missing its argument", token, path.display());
+                    break;
+                };
+                match token.as_str() {
+                    "-I" => self.include_dirs.push(base.join(arg).clean()),
+                    "-D" => self.define(&arg),
+                    "-y" => self.lib_dirs.push(base.join(arg).clean()),
+                    "-v" => self.files.push(base.join(arg).clean()),
+                    // nested lists that can't be read are skipped
+                    "-f" => {
+                        self.read_nested(Path::new(&arg), &base, false, visited, env);
+                    }
+                    _ => {
+                        self.read_nested(Path::new(&arg), &base, true, visited, env);
+                    }
+                }
+            } else if SKIPPED_ARGS.contains(&token.as_str()) {
+                tokens.next();
+            } else if !token.starts_with(['-', '+']) {
+                // the argument of an unknown option isn't a source
+                let file = base.join(&token).clean();
+                if file.is_file() {
+                    self.files.push(file);
+                } else {
+                    warn!("skipping {} in {}, it isn't a file", token, path.display());
+                }
+            }
+        }
+        true
+    }
+
+    fn define(&mut self, define: &str) {
+        match define.split_once('=') {
+            Some((name, value)) => self.defines.insert(name.to_owned(), Some(value.to_owned())),
+            None => self.defines.insert(define.to_owned(), None),
+        };
+    }
+}
+
+/// the arguments of a plusarg, ex. `a+b` in `+incdir+a+b`
+fn plus_args(args: &str) -> impl Iterator<Item = &str> {
+    args.split('+').filter(|x| !x.is_empty())
+}
+
+/// split a file list into arguments, removing `//`, `/* */` and `#` comments
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c == '"' {
+                quoted = false;
+            } else {
+                token.push(c);
+            }
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|x| *x == '\n');
+                tokens.extend(Some(std::mem::take(&mut token)).filter(|x| !x.is_empty()));
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                tokens.extend(Some(std::mem::take(&mut token)).filter(|x| !x.is_empty()));
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            '#' if token.is_empty() => {
+                chars.by_ref().find(|x| *x == '\n');
+            }
+            c if c.is_whitespace() => {
+                tokens.extend(Some(std::mem::take(&mut token)).filter(|x| !x.is_empty()));
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// expand `$VAR`, `${VAR}` and `$(VAR)`, unset variables are left as they are
+fn expand_env(token: &str, env: &dyn Fn(&str) -> Option<String>) -> String {
+    static VAR: OnceLock<Regex> = OnceLock::new();
+    let var = VAR.get_or_init(|| {
+        Regex::new(r"\$(?:\{(?P<braced>\w+)\}|\((?P<paren>\w+)\)|(?P<name>\w+))").unwrap()
+    });
+    var.replace_all(token, |caps: &Captures| {
+        let name = caps
+            .name("braced")
+            .or_else(|| caps.name("paren"))
+            .or_else(|| caps.name("name"))
+            .unwrap()
+            .as_str();
+        env(name).unwrap_or_else(|| caps[0].to_owned())
+    })
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_file_list() {
+        let dir = TempDir::new("file_list_test").unwrap();
+        let root = dir.path();
+        create_dir_all(root.join("ip/rtl")).unwrap();
+        create_dir_all(root.join("lib")).unwrap();
+        write(root.join("lib/cells.v"), "").unwrap();
+        write(root.join("lib/cells.sv"), "").unwrap();
+        write(root.join("lib/notes.txt"), "").unwrap();
+        write(root.join("ip/rtl/ip.sv"), "").unwrap();
+        write(root.join("top.sv"), "").unwrap();
+        let env = |name: &str| (name == "FILE_LIST_TEST_ROOT").then(|| root.display().to_string());
+        write(
+            root.join("ip/ip.f"),
+            "// relative to this list\n+incdir+rtl/inc// comment\nrtl/ip.sv/* block\ncomment */-F ip.f\n",
+        )
+        .unwrap();
+        write(
+            root.join("top.f"),
+            r#"# the top level list
++incdir+${FILE_LIST_TEST_ROOT}/inc+$(FILE_LIST_TEST_ROOT)/inc2
+-I$FILE_LIST_TEST_ROOT/inc3
++define+SIMULATION+WIDTH=8
+-DSYNTHESIS=0 -D FAST
+--top-module top -sv
++libext+.sv
+-y $FILE_LIST_TEST_ROOT/lib
+-v $FILE_LIST_TEST_ROOT/lib/cells.v
+-F $FILE_LIST_TEST_ROOT/ip/ip.f
+"$FILE_LIST_TEST_ROOT/top.sv"
+"#,
+        )
+        .unwrap();
+        let list = FileList::read_with_env(&root.join("top.f"), &env).unwrap();
+        assert_eq!(
+            list.include_dirs,
+            vec![
+                root.join("inc"),
+                root.join("inc2"),
+                root.join("inc3"),
+                root.join("ip/rtl/inc"),
+            ]
+        );
+        assert_eq!(
+            list.files,
+            vec![
+                root.join("lib/cells.v"),
+                root.join("ip/rtl/ip.sv"),
+                root.join("top.sv"),
+            ]
+        );
+        assert_eq!(list.lib_dirs, vec![root.join("lib")]);
+        assert_eq!(list.libext, vec![".sv".to_owned()]);
+        assert_eq!(
+            list.defines,
+            HashMap::from([
+                ("SIMULATION".to_owned(), None),
+                ("WIDTH".to_owned(), Some("8".to_owned())),
+                ("SYNTHESIS".to_owned(), Some("0".to_owned())),
+                ("FAST".to_owned(), None),
+            ])
+        );
+        assert_eq!(
+            list.sources(),
+            vec![
+                root.join("lib/cells.v"),
+                root.join("ip/rtl/ip.sv"),
+                root.join("top.sv"),
+                root.join("lib/cells.sv"),
+            ]
+        );
+        assert!(FileList::read(&root.join("missing.f")).is_none());
+    }
+
+    #[test]
+    fn test_skipped_args() {
+        let dir = TempDir::new("file_list_test").unwrap();
+        let root = dir.path();
+        write(root.join("top.sv"), "").unwrap();
+        // the arguments of unknown options are skipped as they aren't files
+        write(
+            root.join("top.f"),
+            format!(
+                "-timescale 1ns/1ps --x-assign 0 -j 4 +verilog2001ext+v -sverilog\n\
+                 --unknown-option value\n{}\n",
+                root.join("top.sv").display()
+            ),
+        )
+        .unwrap();
+        let list = FileList::read(&root.join("top.f")).unwrap();
+        assert_eq!(list.files, vec![root.join("top.sv")]);
+    }
+
+    #[test]
+    fn test_expand_env() {
+        let env = |name: &str| (name == "ROOT").then(|| "/work".to_owned());
+        assert_eq!(
+            expand_env("$ROOT/a+${ROOT}/b+$(ROOT)/c", &env),
+            "/work/a+/work/b+/work/c"
+        );
+        assert_eq!(expand_env("$UNSET/a", &env), "$UNSET/a");
+    }
+}
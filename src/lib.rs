@@ -1,8 +1,10 @@
 #![recursion_limit = "256"]
 
+pub mod code_action;
 pub mod completion;
 pub mod definition;
 pub mod diagnostics;
+pub mod file_list;
 pub mod format;
 pub mod server;
 pub mod sources;
@@ -5,9 +5,11 @@ use std::sync::Arc;
 use structopt::StructOpt;
 use tower_lsp::{LspService, Server};
 
+mod code_action;
 mod completion;
 mod definition;
 mod diagnostics;
+mod file_list;
 mod format;
 mod server;
 mod sources;
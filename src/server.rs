@@ -1,19 +1,28 @@
 use crate::sources::*;
 
+use crate::code_action::ADD_SOURCE_DIR;
 use crate::completion::keyword::*;
+use crate::diagnostics::{CancelToken, PulledReport, Workspace};
+use crate::file_list::FileList;
 use flexi_logger::LoggerHandle;
 use log::{debug, info, warn};
 use path_clean::PathClean;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::env::current_dir;
 use std::fs::File;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::string::ToString;
-use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 use tower_lsp::jsonrpc::{Error, ErrorCode, Result};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
+#[cfg(feature = "veridian_slang")]
+use veridian_slang::SlangDesign;
 use which::which;
 
 pub struct LSPServer {
@@ -23,6 +32,21 @@ pub struct LSPServer {
     pub directives: Vec<CompletionItem>,
     pub conf: RwLock<ProjectConfig>,
     pub log_handle: Mutex<Option<LoggerHandle>>,
+    // other files that received diagnostics while linting a file
+    pub published: Mutex<HashMap<Url, HashSet<Url>>>,
+    // in-flight diagnostics runs started by document changes
+    pub pending_diagnostics: Mutex<HashMap<Url, CancelToken>>,
+    // diagnostics last reported to pull requests
+    pub pulled: Mutex<HashMap<Url, PulledReport>>,
+    // the client pulls diagnostics, so they are never published
+    pub pull_diagnostics: AtomicBool,
+    // the workspace root, where code actions look for the config
+    pub root: RwLock<Option<PathBuf>>,
+    // the names and definitions of the workspace, with the source generation they were found in
+    pub workspace: Mutex<Option<(usize, Arc<Workspace>)>>,
+    // the last elaborated design, with the document and source generation it was elaborated for
+    #[cfg(feature = "veridian_slang")]
+    pub design: Mutex<Option<(Url, usize, Arc<SlangDesign>)>>,
 }
 
 impl LSPServer {
@@ -34,20 +58,28 @@ impl LSPServer {
             directives: other_completions(DIRECTIVES),
             conf: RwLock::new(ProjectConfig::default()),
             log_handle: Mutex::new(log_handle),
+            published: Mutex::new(HashMap::new()),
+            pending_diagnostics: Mutex::new(HashMap::new()),
+            pulled: Mutex::new(HashMap::new()),
+            pull_diagnostics: AtomicBool::new(false),
+            root: RwLock::new(None),
+            workspace: Mutex::new(None),
+            #[cfg(feature = "veridian_slang")]
+            design: Mutex::new(None),
         }
     }
 }
 
 pub struct Backend {
     client: Client,
-    server: LSPServer,
+    server: Arc<LSPServer>,
 }
 
 impl Backend {
     pub fn new(client: Client, log_handle: LoggerHandle) -> Backend {
         Backend {
             client,
-            server: LSPServer::new(Some(log_handle)),
+            server: Arc::new(LSPServer::new(Some(log_handle))),
         }
     }
 }
@@ -75,10 +107,37 @@ pub struct ProjectConfig {
     pub include_dirs: Vec<String>,
     // list of directories to recursively search for SystemVerilog/Verilog sources
     pub source_dirs: Vec<String>,
+    // list of source files, if files, include or file_lists are set only the configured sources
+    // are indexed, instead of searching source_dirs and include_dirs
+    pub files: Vec<String>,
+    // globs of sources to index, ex. rtl/**/*.sv
+    pub include: Vec<String>,
+    // globs of sources that are never indexed, ex. **/generated/**
+    pub exclude: Vec<String>,
+    // verilator/VCS style file lists (.f) with sources, include dirs and defines
+    pub file_lists: Vec<String>,
+    // source extensions and their language standard, in addition to sv and svh as 1800-2017
+    // and v and vh as 1364-2005
+    pub extensions: HashMap<String, LanguageStandard>,
+    // preprocessor defines, ex. SIMULATION or WIDTH: 8, a define without a value is null
+    #[serde(deserialize_with = "define_values")]
+    pub defines: HashMap<String, Option<String>>,
     // config options for verible tools
     pub verible: Verible,
     // config options for verilator tools
     pub verilator: Verilator,
+    // config options for slang
+    pub slang: Slang,
+    // user defined external linters
+    pub linters: Vec<Linter>,
+    // run diagnostics while typing, not only on open and save
+    pub lint_on_change: LintOnChange,
+    // severity of diagnostics by rule code
+    pub diagnostics: DiagnosticsConfig,
+    // built-in RTL coding rules
+    pub rtl_rules: RtlRules,
+    // regexes declaration names have to match
+    pub naming: Naming,
     // log level
     pub log_level: LogLevel,
 }
@@ -89,13 +148,115 @@ impl Default for ProjectConfig {
             auto_search_workdir: true,
             include_dirs: Vec::new(),
             source_dirs: Vec::new(),
+            files: Vec::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            file_lists: Vec::new(),
+            extensions: default_extensions(),
+            defines: HashMap::new(),
             verible: Verible::default(),
             verilator: Verilator::default(),
+            slang: Slang::default(),
+            linters: Vec::new(),
+            lint_on_change: LintOnChange::default(),
+            diagnostics: DiagnosticsConfig::default(),
+            rtl_rules: RtlRules::default(),
+            naming: Naming::default(),
             log_level: LogLevel::Info,
         }
     }
 }
 
+impl ProjectConfig {
+    /// the language standard of a source file, None if it isn't a source
+    pub fn language(&self, path: &Path) -> Option<LanguageStandard> {
+        language(&self.extensions, path)
+    }
+}
+
+/// language standards, named as verilator's --default-language
+#[derive(
+    strum_macros::Display,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+pub enum LanguageStandard {
+    #[strum(serialize = "1364-1995")]
+    #[serde(rename = "1364-1995", alias = "verilog-1995")]
+    Verilog1995,
+    #[strum(serialize = "1364-2001")]
+    #[serde(rename = "1364-2001", alias = "verilog-2001")]
+    Verilog2001,
+    #[strum(serialize = "1364-2005")]
+    #[serde(rename = "1364-2005", alias = "verilog-2005")]
+    Verilog2005,
+    #[strum(serialize = "1800-2005")]
+    #[serde(rename = "1800-2005", alias = "systemverilog-2005")]
+    SystemVerilog2005,
+    #[strum(serialize = "1800-2009")]
+    #[serde(rename = "1800-2009", alias = "systemverilog-2009")]
+    SystemVerilog2009,
+    #[strum(serialize = "1800-2012")]
+    #[serde(rename = "1800-2012", alias = "systemverilog-2012")]
+    SystemVerilog2012,
+    #[strum(serialize = "1800-2017")]
+    #[serde(rename = "1800-2017", alias = "systemverilog-2017")]
+    SystemVerilog2017,
+}
+
+pub fn default_extensions() -> HashMap<String, LanguageStandard> {
+    HashMap::from([
+        ("sv".to_owned(), LanguageStandard::SystemVerilog2017),
+        ("svh".to_owned(), LanguageStandard::SystemVerilog2017),
+        ("v".to_owned(), LanguageStandard::Verilog2005),
+        ("vh".to_owned(), LanguageStandard::Verilog2005),
+    ])
+}
+
+/// the language standard of a file by its extension, None if it isn't a source
+pub fn language(
+    extensions: &HashMap<String, LanguageStandard>,
+    path: &Path,
+) -> Option<LanguageStandard> {
+    extensions.get(path.extension()?.to_str()?).copied()
+}
+
+/// define values written as numbers or booleans are read as their text
+fn define_values<'de, D>(
+    deserializer: D,
+) -> std::result::Result<HashMap<String, Option<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let defines: HashMap<String, Option<serde_yaml::Value>> =
+        Deserialize::deserialize(deserializer)?;
+    defines
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                None | Some(serde_yaml::Value::Null) => None,
+                Some(serde_yaml::Value::String(x)) => Some(x),
+                Some(serde_yaml::Value::Number(x)) => Some(x.to_string()),
+                Some(serde_yaml::Value::Bool(x)) => Some(x.to_string()),
+                Some(_) => {
+                    return Err(serde::de::Error::custom(format!(
+                        "the value of define {} isn't a scalar",
+                        name
+                    )))
+                }
+            };
+            Ok((name, value))
+        })
+        .collect()
+}
+
 #[derive(Default, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Verible {
@@ -149,6 +310,150 @@ impl Default for VerilatorSyntax {
     }
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Slang {
+    // include dirs in addition to the project include dirs
+    pub include_dirs: Vec<String>,
+    // macros as NAME or NAME=VALUE
+    pub defines: Vec<String>,
+    // warning options, ex. -Weverything or -Wno-width-trunc
+    pub warnings: Vec<String>,
+    // top modules to elaborate, default: automatically determined
+    pub top_modules: Vec<String>,
+    // ex. 1800-2017 or 1800-2023, default: slang default
+    pub language_version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Linter {
+    // name of the linter, used as the diagnostic source
+    pub name: String,
+    pub enabled: bool,
+    pub path: String,
+    // ${file} is replaced by the file path, ${includes} is repeated for every include dir and
+    // ${defines} for every define, as NAME or NAME=VALUE
+    pub args: Vec<String>,
+    // if true, the document text is written to stdin
+    pub stdin: bool,
+    // regex with the named captures file, line, col, severity, code and message
+    pub regex: String,
+    // severity used if the regex does not capture one
+    pub severity: LinterSeverity,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self {
+            name: "linter".to_string(),
+            enabled: true,
+            path: String::new(),
+            args: vec!["${file}".to_string()],
+            stdin: false,
+            regex: String::new(),
+            severity: LinterSeverity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinterSeverity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl From<LinterSeverity> for DiagnosticSeverity {
+    fn from(severity: LinterSeverity) -> Self {
+        match severity {
+            LinterSeverity::Error => DiagnosticSeverity::ERROR,
+            LinterSeverity::Warning => DiagnosticSeverity::WARNING,
+            LinterSeverity::Info => DiagnosticSeverity::INFORMATION,
+            LinterSeverity::Hint => DiagnosticSeverity::HINT,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiagnosticsConfig {
+    // rule code or diagnostic source to severity, "*" matches every rule
+    pub rules: HashMap<String, RuleSeverity>,
+    // rules for files matching a glob, later overrides take precedence
+    pub overrides: Vec<DiagnosticsOverride>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiagnosticsOverride {
+    pub glob: String,
+    pub rules: HashMap<String, RuleSeverity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleSeverity {
+    #[serde(alias = "error")]
+    Error,
+    #[serde(alias = "warning")]
+    Warning,
+    #[serde(alias = "info")]
+    Info,
+    #[serde(alias = "hint")]
+    Hint,
+    #[serde(alias = "off")]
+    Off,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RtlRules {
+    // the severity of each rule is set, or the rule turned off, in `diagnostics.rules`
+    pub enabled: bool,
+}
+
+impl Default for RtlRules {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Naming {
+    // a regex by kind of declaration, empty: not checked
+    pub modules: String,
+    pub ports: String,
+    pub nets: String,
+    pub variables: String,
+    pub subroutines: String,
+    pub parameters: String,
+    // signals a sequential block is triggered on first
+    pub clocks: String,
+    // signals a sequential block is triggered on after the clock
+    pub resets: String,
+    // resets triggered on a falling edge, ex. _n$
+    pub active_low: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintOnChange {
+    pub enabled: bool,
+    // time to wait after the last change before linting
+    pub debounce_ms: u64,
+}
+
+impl Default for LintOnChange {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            debounce_ms: 500,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct VeribleFormat {
@@ -194,6 +499,43 @@ fn read_config(root_uri: Option<Url>) -> anyhow::Result<ProjectConfig> {
     Ok(serde_yaml::from_str(&contents)?)
 }
 
+/// the config in effect, with the sources, include dirs, library extensions and defines of the
+/// file lists merged in and the configured extensions added to the defaults. The lint backends
+/// take their include dirs, extensions and defines from it
+fn effective_config(mut conf: ProjectConfig) -> ProjectConfig {
+    let mut defines: HashMap<String, Option<String>> = HashMap::new();
+    let mut libext: Vec<String> = Vec::new();
+    for path in &conf.file_lists {
+        let Some(list) = absolute_path(path).and_then(|x| FileList::read(&x)) else {
+            warn!("couldn't read file list {}", path);
+            continue;
+        };
+        conf.include_dirs
+            .extend(list.include_dirs.iter().map(|x| x.display().to_string()));
+        conf.files
+            .extend(list.sources().iter().map(|x| x.display().to_string()));
+        defines.extend(list.defines);
+        libext.extend(list.libext);
+    }
+    let mut extensions = default_extensions();
+    // library extensions of the file lists that aren't known are read as 1800-2017
+    for ext in libext {
+        extensions
+            .entry(ext.trim_start_matches('.').to_owned())
+            .or_insert(LanguageStandard::SystemVerilog2017);
+    }
+    extensions.extend(
+        conf.extensions
+            .drain()
+            .map(|(ext, std)| (ext.trim_start_matches('.').to_owned(), std)),
+    );
+    conf.extensions = extensions;
+    // the config takes precedence over the file lists
+    defines.extend(conf.defines.drain());
+    conf.defines = defines;
+    conf
+}
+
 // convert string path to absolute path
 fn absolute_path(path_str: &str) -> Option<PathBuf> {
     let path = PathBuf::from(path_str);
@@ -213,11 +555,29 @@ impl LanguageServer for Backend {
         // grab include dirs and source dirs from config, and convert to abs path
         let mut inc_dirs = self.server.srcs.include_dirs.write().unwrap();
         let mut src_dirs = self.server.srcs.source_dirs.write().unwrap();
+        *self.server.root.write().unwrap() = params
+            .root_uri
+            .iter()
+            .chain(params.workspace_folders.iter().flatten().map(|x| &x.uri))
+            .find_map(|x| x.to_file_path().ok());
         match read_config(params.root_uri) {
             Ok(conf) => {
+                let conf = effective_config(conf);
                 inc_dirs.extend(conf.include_dirs.iter().filter_map(|x| absolute_path(x)));
-                debug!("{:#?}", inc_dirs);
                 src_dirs.extend(conf.source_dirs.iter().filter_map(|x| absolute_path(x)));
+                self.server
+                    .srcs
+                    .source_files
+                    .write()
+                    .unwrap()
+                    .extend(conf.files.iter().filter_map(|x| absolute_path(x)));
+                *self.server.srcs.include.write().unwrap() = conf.include.clone();
+                *self.server.srcs.exclude.write().unwrap() = conf.exclude.clone();
+                // completion and the linters read the extensions from the config, source
+                // discovery and code actions from the sources, both get the merged extensions
+                *self.server.srcs.extensions.write().unwrap() = conf.extensions.clone();
+                *self.server.srcs.defines.write().unwrap() = conf.defines.clone();
+                debug!("{:#?}", inc_dirs);
                 debug!("{:#?}", src_dirs);
                 let mut log_handle = self.server.log_handle.lock().unwrap();
                 let log_handle = log_handle.as_mut();
@@ -238,7 +598,7 @@ impl LanguageServer for Backend {
         }
         let mut conf = self.server.conf.write().unwrap();
         conf.verible.syntax.enabled = which(&conf.verible.syntax.path).is_ok();
-        if cfg!(feature = "slang") {
+        if cfg!(feature = "veridian_slang") {
             info!("enabled linting with slang");
         }
         if conf.verilator.syntax.enabled {
@@ -256,6 +616,14 @@ impl LanguageServer for Backend {
         drop(src_dirs);
         // parse all source files found from walking source dirs and include dirs
         self.server.srcs.init();
+        let pull_diagnostics = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .is_some_and(|x| x.diagnostic.is_some());
+        self.server
+            .pull_diagnostics
+            .store(pull_diagnostics, Ordering::SeqCst);
         Ok(InitializeResult {
             server_info: None,
             capabilities: ServerCapabilities {
@@ -290,6 +658,25 @@ impl LanguageServer for Backend {
                 document_formatting_provider: Some(OneOf::Left(conf.verible.format.enabled)),
                 document_range_formatting_provider: Some(OneOf::Left(conf.verible.format.enabled)),
                 document_highlight_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![ADD_SOURCE_DIR.to_string()],
+                    work_done_progress_options: WorkDoneProgressOptions {
+                        work_done_progress: None,
+                    },
+                }),
+                // diagnostics are pushed to clients that can't pull them
+                diagnostic_provider: pull_diagnostics.then(|| {
+                    DiagnosticServerCapabilities::Options(DiagnosticOptions {
+                        identifier: Some("veridian".to_string()),
+                        inter_file_dependencies: true,
+                        workspace_diagnostics: true,
+                        work_done_progress_options: WorkDoneProgressOptions {
+                            work_done_progress: None,
+                        },
+                    })
+                }),
                 ..ServerCapabilities::default()
             },
         })
@@ -303,27 +690,62 @@ impl LanguageServer for Backend {
         Ok(())
     }
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        let diagnostics = self.server.did_open(params);
-        self.client
-            .publish_diagnostics(
-                diagnostics.uri,
-                diagnostics.diagnostics,
-                diagnostics.version,
-            )
-            .await;
+        for diagnostics in self.server.did_open(params) {
+            self.client
+                .publish_diagnostics(
+                    diagnostics.uri,
+                    diagnostics.diagnostics,
+                    diagnostics.version,
+                )
+                .await;
+        }
     }
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        let uri = params.text_document.uri.clone();
+        let version = params.text_document.version;
         self.server.did_change(params);
+        let debounce = {
+            let conf = self.server.conf.read().unwrap();
+            if !conf.lint_on_change.enabled || self.server.pull_diagnostics.load(Ordering::SeqCst) {
+                return;
+            }
+            Duration::from_millis(conf.lint_on_change.debounce_ms)
+        };
+        let cancel = self.server.start_change_diagnostics(&uri);
+        let server = self.server.clone();
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(debounce).await;
+            if cancel.is_cancelled() {
+                return;
+            }
+            let result = tokio::task::spawn_blocking(move || {
+                server.did_change_diagnostics(uri, version, &cancel)
+            })
+            .await;
+            if let Ok(Some(params)) = result {
+                for diagnostics in params {
+                    client
+                        .publish_diagnostics(
+                            diagnostics.uri,
+                            diagnostics.diagnostics,
+                            diagnostics.version,
+                        )
+                        .await;
+                }
+            }
+        });
     }
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
-        let diagnostics = self.server.did_save(params);
-        self.client
-            .publish_diagnostics(
-                diagnostics.uri,
-                diagnostics.diagnostics,
-                diagnostics.version,
-            )
-            .await;
+        for diagnostics in self.server.did_save(params) {
+            self.client
+                .publish_diagnostics(
+                    diagnostics.uri,
+                    diagnostics.diagnostics,
+                    diagnostics.version,
+                )
+                .await;
+        }
     }
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         Ok(self.server.completion(params))
@@ -358,6 +780,50 @@ impl LanguageServer for Backend {
     ) -> Result<Option<Vec<DocumentHighlight>>> {
         Ok(self.server.document_highlight(params))
     }
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        Ok(self.server.references(params))
+    }
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        Ok(self.server.code_action(params))
+    }
+    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
+        let server = self.server.clone();
+        let published = tokio::task::spawn_blocking(move || server.execute_command(params))
+            .await
+            .map_err(|_| Error::internal_error())?;
+        for diagnostics in published {
+            self.client
+                .publish_diagnostics(
+                    diagnostics.uri,
+                    diagnostics.diagnostics,
+                    diagnostics.version,
+                )
+                .await;
+        }
+        // new sources can change the diagnostics of every file
+        if self.server.pull_diagnostics.load(Ordering::SeqCst) {
+            let _ = self.client.workspace_diagnostic_refresh().await;
+        }
+        Ok(None)
+    }
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> Result<DocumentDiagnosticReportResult> {
+        let server = self.server.clone();
+        tokio::task::spawn_blocking(move || server.document_diagnostic(params))
+            .await
+            .map_err(|_| Error::internal_error())
+    }
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> Result<WorkspaceDiagnosticReportResult> {
+        let server = self.server.clone();
+        tokio::task::spawn_blocking(move || server.workspace_diagnostic(params))
+            .await
+            .map_err(|_| Error::internal_error())
+    }
 }
 
 #[cfg(test)]
@@ -382,4 +848,111 @@ log_level: Info
         dbg!(&config);
         assert!(config.is_ok());
     }
+
+    #[test]
+    fn test_config_extensions() {
+        let config = r#"
+extensions:
+  sva: 1800-2017
+  vp: verilog-2001
+"#;
+        let config = serde_yaml::from_str::<ProjectConfig>(config).unwrap();
+        assert_eq!(
+            config.language(Path::new("/rtl/props.sva")),
+            Some(LanguageStandard::SystemVerilog2017)
+        );
+        assert_eq!(
+            config.language(Path::new("/rtl/core.vp")),
+            Some(LanguageStandard::Verilog2001)
+        );
+        assert_eq!(config.language(Path::new("/rtl/notes.txt")), None);
+        assert_eq!(LanguageStandard::Verilog2001.to_string(), "1364-2001");
+        assert!(serde_yaml::from_str::<ProjectConfig>("extensions:\n  v: 1364-2023\n").is_err());
+    }
+
+    #[test]
+    fn test_config_defines() {
+        let config = r#"
+defines:
+  SIMULATION:
+  WIDTH: 8
+  MODE: fast
+"#;
+        let config = serde_yaml::from_str::<ProjectConfig>(config).unwrap();
+        assert_eq!(
+            config.defines,
+            HashMap::from([
+                ("SIMULATION".to_owned(), None),
+                ("WIDTH".to_owned(), Some("8".to_owned())),
+                ("MODE".to_owned(), Some("fast".to_owned())),
+            ])
+        );
+        assert!(serde_yaml::from_str::<ProjectConfig>("defines:\n  WIDTH: [8]\n").is_err());
+    }
+
+    #[test]
+    fn test_effective_config() {
+        let dir = tempdir::TempDir::new("effective_config").unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("inc")).unwrap();
+        std::fs::write(root.join("top.sv"), "").unwrap();
+        std::fs::write(
+            root.join("top.f"),
+            format!(
+                "+incdir+{}\n+define+WIDTH=4+SIMULATION\n+libext+.svp+.v\n{}\n",
+                root.join("inc").display(),
+                root.join("top.sv").display()
+            ),
+        )
+        .unwrap();
+        let config = format!(
+            r#"
+include_dirs:
+  - rtl/inc
+file_lists:
+  - {}
+  - {}
+extensions:
+  .sva: 1800-2017
+  v: 1364-2001
+defines:
+  WIDTH: 8
+"#,
+            root.join("top.f").display(),
+            root.join("missing.f").display()
+        );
+        let conf = effective_config(serde_yaml::from_str::<ProjectConfig>(&config).unwrap());
+        assert_eq!(
+            conf.include_dirs,
+            vec!["rtl/inc".to_owned(), root.join("inc").display().to_string()]
+        );
+        assert_eq!(conf.files, vec![root.join("top.sv").display().to_string()]);
+        // a define of the config overrides the one of the file list
+        assert_eq!(
+            conf.defines,
+            HashMap::from([
+                ("WIDTH".to_owned(), Some("8".to_owned())),
+                ("SIMULATION".to_owned(), None),
+            ])
+        );
+        // configured extensions are added to the defaults and the library extensions of the file
+        // lists, and override their standard
+        assert_eq!(
+            conf.language(Path::new("/rtl/props.sva")),
+            Some(LanguageStandard::SystemVerilog2017)
+        );
+        assert_eq!(
+            conf.language(Path::new("/rtl/core.v")),
+            Some(LanguageStandard::Verilog2001)
+        );
+        assert_eq!(
+            conf.language(Path::new("/rtl/core.sv")),
+            Some(LanguageStandard::SystemVerilog2017)
+        );
+        assert_eq!(
+            conf.language(Path::new("/rtl/core.svp")),
+            Some(LanguageStandard::SystemVerilog2017)
+        );
+        assert_eq!(conf.language(Path::new("/rtl/notes.txt")), None);
+    }
 }
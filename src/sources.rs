@@ -1,7 +1,8 @@
 use crate::definition::def_types::*;
 use crate::definition::get_scopes;
-use crate::diagnostics::{get_diagnostics, is_hidden};
-use crate::server::LSPServer;
+use crate::diagnostics::{get_diagnostics, is_hidden, CancelToken};
+use crate::server::{default_extensions, language, LSPServer, LanguageStandard};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use log::{debug, error, trace};
 use pathdiff::diff_paths;
 use ropey::{Rope, RopeSlice};
@@ -10,7 +11,8 @@ use std::collections::HashMap;
 use std::env::current_dir;
 use std::fs;
 use std::ops::Range as StdRange;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::thread;
 use std::time::Instant;
@@ -19,7 +21,7 @@ use tower_lsp::lsp_types::*;
 use walkdir::WalkDir;
 
 impl LSPServer {
-    pub fn did_open(&self, params: DidOpenTextDocumentParams) -> PublishDiagnosticsParams {
+    pub fn did_open(&self, params: DidOpenTextDocumentParams) -> Vec<PublishDiagnosticsParams> {
         let document: TextDocumentItem = params.text_document;
         let uri = document.uri.clone();
         debug!("did_open: {}", &uri);
@@ -37,12 +39,26 @@ impl LSPServer {
         } else {
             self.srcs.add(document);
         }
+        // clients pulling diagnostics request them themselves
+        if self.pull_diagnostics.load(Ordering::SeqCst) {
+            return Vec::new();
+        }
         // diagnostics
         let urls = self.srcs.names.read().unwrap().keys().cloned().collect();
+        let open_files = self.srcs.open_buffers();
+        let semantic = self.semantic_diagnostics(&uri);
         let file_id = self.srcs.get_id(&uri);
         let file = self.srcs.get_file(file_id).unwrap();
         let file = file.read().unwrap();
-        get_diagnostics(uri, &file.text, urls, &self.conf.read().unwrap())
+        self.track_published(get_diagnostics(
+            uri,
+            &file.text,
+            urls,
+            open_files,
+            semantic,
+            &self.conf.read().unwrap(),
+            &CancelToken::default(),
+        ))
     }
 
     pub fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -66,21 +82,95 @@ impl LSPServer {
         let meta_data = self.srcs.get_meta_data(file_id).unwrap();
         let (lock, cvar) = &*meta_data.read().unwrap().valid_parse;
         let mut valid = lock.lock().unwrap();
+        if *valid {
+            *self.srcs.unparsed.0.lock().unwrap() += 1;
+        }
+        // after the file is counted as unparsed, so nothing is cached for the new generation
+        // before it is indexed again
+        self.srcs.generation.fetch_add(1, Ordering::SeqCst);
         *valid = false;
         cvar.notify_all();
     }
 
-    pub fn did_save(&self, params: DidSaveTextDocumentParams) -> PublishDiagnosticsParams {
+    pub fn did_save(&self, params: DidSaveTextDocumentParams) -> Vec<PublishDiagnosticsParams> {
+        if self.pull_diagnostics.load(Ordering::SeqCst) {
+            return Vec::new();
+        }
         let urls = self.srcs.names.read().unwrap().keys().cloned().collect();
+        let open_files = self.srcs.open_buffers();
+        let semantic = self.semantic_diagnostics(&params.text_document.uri);
         let file_id = self.srcs.get_id(&params.text_document.uri);
         let file = self.srcs.get_file(file_id).unwrap();
         let file = file.read().unwrap();
-        get_diagnostics(
+        self.track_published(get_diagnostics(
             params.text_document.uri,
             &file.text,
             urls,
+            open_files,
+            semantic,
             &self.conf.read().unwrap(),
-        )
+            &CancelToken::default(),
+        ))
+    }
+
+    /// start a diagnostics run for a changed document, cancelling the previous run for it
+    pub fn start_change_diagnostics(&self, uri: &Url) -> CancelToken {
+        let token = CancelToken::default();
+        if let Some(prev) = self
+            .pending_diagnostics
+            .lock()
+            .unwrap()
+            .insert(uri.clone(), token.clone())
+        {
+            prev.cancel();
+        }
+        token
+    }
+
+    /// run diagnostics on the current text of a changed document. Returns None if the run was
+    /// cancelled or the document changed in the meantime, so stale results are never published
+    pub fn did_change_diagnostics(
+        &self,
+        uri: Url,
+        version: i32,
+        cancel: &CancelToken,
+    ) -> Option<Vec<PublishDiagnosticsParams>> {
+        let urls = self.srcs.names.read().unwrap().keys().cloned().collect();
+        let open_files = self.srcs.open_buffers();
+        let file_id = self.srcs.get_id(&uri);
+        let text = {
+            let file = self.srcs.get_file(file_id)?;
+            let file = file.read().unwrap();
+            if file.version != version {
+                return None;
+            }
+            file.text.clone()
+        };
+        let mut diagnostics = get_diagnostics(
+            uri.clone(),
+            &text,
+            urls,
+            open_files,
+            self.semantic_diagnostics(&uri),
+            &self.conf.read().unwrap(),
+            cancel,
+        );
+        let file = self.srcs.get_file(file_id)?;
+        if cancel.is_cancelled() || file.read().unwrap().version != version {
+            debug!("dropping stale diagnostics: {}", &uri);
+            return None;
+        }
+        for params in diagnostics.iter_mut() {
+            if params.uri == uri {
+                params.version = Some(version);
+            }
+        }
+        let mut pending = self.pending_diagnostics.lock().unwrap();
+        if pending.get(&uri).is_some_and(|x| !x.is_cancelled()) {
+            pending.remove(&uri);
+        }
+        drop(pending);
+        Some(self.track_published(diagnostics))
     }
 }
 
@@ -102,27 +192,78 @@ pub struct SourceMeta {
 }
 
 /// find SystemVerilog/Verilog sources recursively from opened files
-fn find_src_paths(dirs: &[PathBuf]) -> Vec<PathBuf> {
+fn find_src_paths(
+    dirs: &[PathBuf],
+    extensions: &HashMap<String, LanguageStandard>,
+) -> Vec<PathBuf> {
     let mut paths: Vec<PathBuf> = Vec::new();
 
     for dir in dirs {
         let walker = WalkDir::new(dir).into_iter();
         for entry in walker.filter_entry(|e| !is_hidden(e)) {
             let entry = entry.unwrap();
-            if entry.file_type().is_file() && entry.path().extension().is_some() {
-                let extension = entry.path().extension().unwrap();
+            if entry.file_type().is_file() && language(extensions, entry.path()).is_some() {
+                let entry_path = entry.path().to_path_buf();
+                if !paths.contains(&entry_path) {
+                    paths.push(entry_path);
+                }
+            }
+        }
+    }
+    paths
+}
 
-                if extension == "sv" || extension == "svh" || extension == "v" || extension == "vh"
+/// find SystemVerilog/Verilog sources matching globs, relative globs are relative to the
+/// working directory. Only the directories before the first wildcard of each glob are walked
+fn find_glob_paths(
+    globs: &[String],
+    extensions: &HashMap<String, LanguageStandard>,
+) -> Vec<PathBuf> {
+    let set = glob_set(globs);
+    let roots: Vec<PathBuf> = globs
+        .iter()
+        .filter_map(|glob| {
+            let mut root = current_dir().ok()?;
+            for component in Path::new(glob).components() {
+                if component
+                    .as_os_str()
+                    .to_str()
+                    .is_some_and(|x| x.contains(['*', '?', '[', '{']))
                 {
-                    let entry_path = entry.path().to_path_buf();
-                    if !paths.contains(&entry_path) {
-                        paths.push(entry_path);
-                    }
+                    break;
                 }
+                root.push(component);
+            }
+            Some(root)
+        })
+        .collect();
+    find_src_paths(&roots, extensions)
+        .into_iter()
+        .filter(|x| glob_match(&set, x))
+        .collect()
+}
+
+fn glob_set(globs: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        match Glob::new(glob) {
+            Ok(glob) => {
+                builder.add(glob);
             }
+            Err(e) => error!("invalid glob {}: {}", glob, e),
         }
     }
-    paths
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+/// whether a path matches a glob, relative globs are matched against the path relative to the
+/// working directory
+fn glob_match(set: &GlobSet, path: &Path) -> bool {
+    set.is_match(path)
+        || current_dir()
+            .ok()
+            .and_then(|dir| Some(set.is_match(path.strip_prefix(dir).ok()?)))
+            .unwrap_or(false)
 }
 
 /// The Sources struct manages all source files
@@ -140,6 +281,20 @@ pub struct Sources {
     pub include_dirs: Arc<RwLock<Vec<PathBuf>>>,
     // source directories
     pub source_dirs: Arc<RwLock<Vec<PathBuf>>>,
+    // source files listed in the config, ex. in file lists
+    pub source_files: Arc<RwLock<Vec<PathBuf>>>,
+    // preprocessor defines, passed to parser, a define without a value is None
+    pub defines: Arc<RwLock<HashMap<String, Option<String>>>>,
+    // globs of sources to index instead of walking the source and include directories
+    pub include: Arc<RwLock<Vec<String>>>,
+    // globs of sources that are never indexed
+    pub exclude: Arc<RwLock<Vec<String>>>,
+    // source extensions and their language standard
+    pub extensions: Arc<RwLock<HashMap<String, LanguageStandard>>>,
+    // incremented whenever a file is added or its text changes
+    pub generation: Arc<AtomicUsize>,
+    // number of files waiting to be parsed, every file is indexed once it is zero
+    pub unparsed: Arc<(Mutex<usize>, Condvar)>,
 }
 
 impl std::default::Default for Sources {
@@ -157,9 +312,25 @@ impl Sources {
             scope_tree: Arc::new(RwLock::new(None)),
             include_dirs: Arc::new(RwLock::new(Vec::new())),
             source_dirs: Arc::new(RwLock::new(Vec::new())),
+            source_files: Arc::new(RwLock::new(Vec::new())),
+            defines: Arc::new(RwLock::new(HashMap::new())),
+            include: Arc::new(RwLock::new(Vec::new())),
+            exclude: Arc::new(RwLock::new(Vec::new())),
+            extensions: Arc::new(RwLock::new(default_extensions())),
+            generation: Arc::new(AtomicUsize::new(0)),
+            unparsed: Arc::new((Mutex::new(0), Condvar::new())),
         }
     }
     pub fn init(&self) {
+        let files = self.source_files.read().unwrap().clone();
+        let include = self.include.read().unwrap().clone();
+        // configured sources are indexed instead of the source and include directories
+        if !files.is_empty() || !include.is_empty() {
+            self.index_files(files);
+            let extensions = self.extensions.read().unwrap().clone();
+            self.index_files(find_glob_paths(&include, &extensions));
+            return;
+        }
         let mut paths: Vec<PathBuf> = Vec::new();
         for path in &*self.include_dirs.read().unwrap() {
             paths.push(path.clone());
@@ -168,9 +339,37 @@ impl Sources {
             paths.push(path.clone());
         }
         // find and add all source/header files recursively from configured include and source directories
-        let src_paths = find_src_paths(&paths);
-        for path in src_paths {
+        self.index(&paths);
+    }
+
+    /// add a source directory, indexing the files in it that aren't known yet
+    pub fn add_dir(&self, dir: PathBuf) {
+        let mut source_dirs = self.source_dirs.write().unwrap();
+        if source_dirs.contains(&dir) {
+            return;
+        }
+        source_dirs.push(dir.clone());
+        drop(source_dirs);
+        self.index(&[dir]);
+    }
+
+    /// add the source/header files found in directories, skipping files already added
+    fn index(&self, dirs: &[PathBuf]) {
+        let extensions = self.extensions.read().unwrap().clone();
+        self.index_files(find_src_paths(dirs, &extensions));
+    }
+
+    /// add source files, skipping files already added and excluded files
+    fn index_files(&self, paths: Vec<PathBuf>) {
+        let exclude = glob_set(&self.exclude.read().unwrap());
+        for path in paths {
+            if glob_match(&exclude, &path) {
+                continue;
+            }
             if let Ok(url) = Url::from_file_path(&path) {
+                if self.names.read().unwrap().contains_key(&url) {
+                    continue;
+                }
                 if let Ok(text) = fs::read_to_string(&path) {
                     self.add(TextDocumentItem::new(
                         url,
@@ -203,6 +402,9 @@ impl Sources {
         let source_handle = source.clone();
         let scope_handle = self.scope_tree.clone();
         let inc_dirs = self.include_dirs.clone();
+        let defines = self.defines.clone();
+        let unparsed = self.unparsed.clone();
+        *self.unparsed.0.lock().unwrap() += 1;
 
         // spawn parse thread
         let _ = thread::spawn(move || {
@@ -215,7 +417,13 @@ impl Sources {
                 let range = &file.last_change_range.clone();
                 drop(file);
                 trace!("{}, parse read: {}", uri, now.elapsed().as_millis());
-                let syntax_tree = parse(&text, uri, range, &inc_dirs.read().unwrap());
+                let syntax_tree = parse(
+                    &text,
+                    uri,
+                    range,
+                    &inc_dirs.read().unwrap(),
+                    &defines.read().unwrap(),
+                );
                 let mut scope_tree = match &syntax_tree {
                     Some(tree) => get_scopes(tree, uri),
                     None => None,
@@ -251,6 +459,12 @@ impl Sources {
                     now.elapsed().as_millis()
                 );
                 let mut valid = lock.lock().unwrap();
+                if !*valid {
+                    let (count, indexed) = &*unparsed;
+                    let mut count = count.lock().unwrap();
+                    *count = count.saturating_sub(1);
+                    indexed.notify_all();
+                }
                 *valid = true;
                 cvar.notify_all();
                 while *valid {
@@ -269,6 +483,7 @@ impl Sources {
             })));
         debug!("added {}", &doc.uri);
         self.names.write().unwrap().insert(doc.uri, fid);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
     /// get file by id
@@ -310,6 +525,37 @@ impl Sources {
         }
     }
 
+    /// whether every file is parsed and indexed into the scope tree
+    pub fn is_indexed(&self) -> bool {
+        *self.unparsed.0.lock().unwrap() == 0
+    }
+
+    /// wait until every file is parsed and indexed into the scope tree
+    #[cfg(test)]
+    pub fn wait_indexed(&self) {
+        let (count, indexed) = &*self.unparsed;
+        let mut count = count.lock().unwrap();
+        while *count > 0 {
+            count = indexed.wait(count).unwrap();
+        }
+    }
+
+    /// get the text of all files opened in the editor, files only indexed from disk are skipped
+    pub fn open_buffers(&self) -> Vec<(Url, Rope)> {
+        let files = self.files.read().unwrap();
+        files
+            .iter()
+            .filter_map(|file| {
+                let file = file.read().ok()?;
+                if file.version == -1 {
+                    None
+                } else {
+                    Some((file.uri.clone(), file.text.clone()))
+                }
+            })
+            .collect()
+    }
+
     /// get file id from url
     pub fn get_id(&self, uri: &Url) -> usize {
         *self.names.read().unwrap().get(uri).unwrap()
@@ -350,6 +596,25 @@ impl Sources {
                 .get_dot_completion(token, byte_idx, url, tree.as_ref()?),
         })
     }
+
+    /// compute completions of the parameters that can be overridden when instantiating a
+    /// module, interface or program
+    pub fn get_param_completions(&self, unit: &str) -> Option<CompletionList> {
+        debug!("retrieving parameter completion for: {}", unit);
+        let tree = self.scope_tree.read().ok()?;
+        let scope = tree.as_ref()?.scopes.iter().find(|x| {
+            x.ident() == unit
+                && matches!(x.symbol_kind(), SymbolKind::MODULE | SymbolKind::INTERFACE)
+        })?;
+        Some(CompletionList {
+            is_incomplete: false,
+            items: scope
+                .parameters()
+                .into_iter()
+                .map(|x| x.completion())
+                .collect(),
+        })
+    }
 }
 
 //TODO: show all unrecoverable parse errors to user
@@ -359,19 +624,30 @@ pub fn parse(
     uri: &Url,
     last_change_range: &Option<Range>,
     inc_paths: &[PathBuf],
+    defines: &HashMap<String, Option<String>>,
 ) -> Option<SyntaxTree> {
     let mut parse_iterations = 1;
     let mut i = 0;
     let mut includes: Vec<PathBuf> = inc_paths.to_vec();
     let mut reverted_change = false;
     let mut text = doc.clone();
+    let defines: HashMap<String, Option<Define>> = defines
+        .iter()
+        .map(|(name, value)| {
+            let text = value.as_ref().map(|x| DefineText::new(x.clone(), None));
+            (
+                name.clone(),
+                Some(Define::new(name.clone(), Vec::new(), text)),
+            )
+        })
+        .collect();
 
     while i < parse_iterations {
         i += 1;
         match parse_sv_str(
             &text.to_string(),
             uri.to_file_path().unwrap(),
-            &HashMap::new(),
+            &defines,
             &includes,
             false,
         ) {
@@ -612,6 +888,29 @@ endmodule"#;
             .contains_scope("test"));
     }
 
+    #[test]
+    fn test_wait_indexed() {
+        test_init();
+        let srcs = Sources::new();
+        for i in 0..4 {
+            srcs.add(TextDocumentItem::new(
+                Url::parse(&format!("file:///test{}.sv", i)).unwrap(),
+                "systemverilog".to_owned(),
+                -1,
+                format!("module test{};\nendmodule\n", i),
+            ));
+        }
+        srcs.wait_indexed();
+        assert_eq!(*srcs.unparsed.0.lock().unwrap(), 0);
+        let scope_tree = srcs.scope_tree.read().unwrap();
+        for i in 0..4 {
+            assert!(scope_tree
+                .as_ref()
+                .unwrap()
+                .contains_scope(&format!("test{}", i)));
+        }
+    }
+
     #[test]
     fn test_header() {
         test_init();
@@ -619,7 +918,86 @@ endmodule"#;
         d.push("test_data/top_inc.sv");
         let text = read_to_string(&d).unwrap();
         let doc = Rope::from_str(&text);
-        assert!(parse(&doc, &Url::from_file_path(d).unwrap(), &None, &Vec::new()).is_some(),);
+        assert!(parse(
+            &doc,
+            &Url::from_file_path(d).unwrap(),
+            &None,
+            &Vec::new(),
+            &HashMap::new()
+        )
+        .is_some(),);
         // TODO: add missing header test
     }
+
+    #[test]
+    fn test_configured_sources() {
+        test_init();
+        let dir = tempdir::TempDir::new("configured_sources").unwrap();
+        let root = dir.path();
+        for path in [
+            "rtl/top.sv",
+            "rtl/fifo.v",
+            "rtl/notes.txt",
+            "rtl/generated/regs.sv",
+            "vendor/ip.sv",
+            "obsolete/old.sv",
+        ] {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), "module m;\nendmodule\n").unwrap();
+        }
+        let srcs = Sources::new();
+        srcs.source_dirs.write().unwrap().push(root.to_path_buf());
+        srcs.source_files
+            .write()
+            .unwrap()
+            .push(root.join("vendor/ip.sv"));
+        srcs.include
+            .write()
+            .unwrap()
+            .push(format!("{}/rtl/**", root.display()));
+        srcs.exclude
+            .write()
+            .unwrap()
+            .push("**/generated/**".to_owned());
+        srcs.init();
+        let mut indexed: Vec<PathBuf> = srcs
+            .names
+            .read()
+            .unwrap()
+            .keys()
+            .map(|x| x.to_file_path().unwrap())
+            .collect();
+        indexed.sort();
+        assert_eq!(
+            indexed,
+            vec![
+                root.join("rtl/fifo.v"),
+                root.join("rtl/top.sv"),
+                root.join("vendor/ip.sv"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_defines() {
+        test_init();
+        let doc = Rope::from_str(
+            "`ifdef SIMULATION\nmodule sim;\nendmodule\n`else\nmodule syn;\nendmodule\n`endif\n",
+        );
+        let url = Url::parse("file:///test.sv").unwrap();
+        let modules = |defines: &HashMap<String, Option<String>>| {
+            let tree = parse(&doc, &url, &None, &Vec::new(), defines).unwrap();
+            get_scopes(&tree, &url)
+                .unwrap()
+                .scopes
+                .iter()
+                .map(|x| x.ident())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(modules(&HashMap::new()), vec!["syn"]);
+        assert_eq!(
+            modules(&HashMap::from([("SIMULATION".to_owned(), None)])),
+            vec!["sim"]
+        );
+    }
 }
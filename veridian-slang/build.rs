@@ -6,9 +6,13 @@ use tar::Archive;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+// Keep the version the same as the one in `CMakeLists.txt`
+const SLANG_VERSION: &str = "7.0";
+
+#[cfg(feature = "download")]
 fn download_slang(download_to: &Path) -> Result<PathBuf> {
-    // Keep the version the same as the one in `CMakeLists.txt`
-    let target = "https://github.com/MikePopoloski/slang/archive/refs/tags/v7.0.tar.gz";
+    let target =
+        format!("https://github.com/MikePopoloski/slang/archive/refs/tags/v{SLANG_VERSION}.tar.gz");
 
     fs::create_dir_all(download_to)?;
 
@@ -18,6 +22,22 @@ fn download_slang(download_to: &Path) -> Result<PathBuf> {
     reqwest::blocking::get(target)?.copy_to(&mut dest)?;
     drop(dest);
 
+    unpack_slang(&archive_path, download_to)
+}
+
+#[cfg(not(feature = "download"))]
+fn download_slang(_download_to: &Path) -> Result<PathBuf> {
+    Err(format!(
+        "slang v{SLANG_VERSION} was not found and the `download` feature is disabled, \
+         set SLANG_SRC_PATH to a slang source directory or archive, \
+         or SLANG_INSTALL_PATH to a slang installation"
+    )
+    .into())
+}
+
+fn unpack_slang(archive_path: &Path, download_to: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(download_to)?;
+
     // Unpack archive
     let mut archive = Archive::new(GzDecoder::new(File::open(archive_path)?));
     archive.unpack(download_to)?;
@@ -32,6 +52,43 @@ fn download_slang(download_to: &Path) -> Result<PathBuf> {
     Ok(entries.first().unwrap().path())
 }
 
+/// The slang source to build, from SLANG_SRC_PATH if set, which may be a source directory or a
+/// `.tar.gz` archive, otherwise downloaded
+fn slang_source(out_dir: &Path) -> Result<PathBuf> {
+    let download_dir = out_dir.join("slang-src");
+    match env::var_os("SLANG_SRC_PATH") {
+        Some(src) => {
+            let src = PathBuf::from(src);
+            println!("cargo:rerun-if-changed={}", src.display());
+            if src.is_dir() {
+                Ok(src)
+            } else {
+                unpack_slang(&src, &download_dir)
+            }
+        }
+        None => download_slang(&download_dir),
+    }
+}
+
+/// The installation prefix and library directory of a slang installed on the system, found
+/// with pkg-config
+fn system_slang() -> Option<(PathBuf, PathBuf)> {
+    if env::var_os("SLANG_SRC_PATH").is_some() {
+        return None;
+    }
+    let library = pkg_config::Config::new()
+        .atleast_version(SLANG_VERSION)
+        .cargo_metadata(false)
+        .probe("sv-lang")
+        .ok()?;
+    let lib_dir = library.link_paths.first()?.clone();
+    let prefix = pkg_config::get_variable("sv-lang", "prefix")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| lib_dir.parent().map(Path::to_path_buf))?;
+    Some((prefix, lib_dir))
+}
+
 fn build_slang(slang_src: &Path, slang_install: &Path) {
     cmake::Config::new(slang_src)
         .profile("Release")
@@ -51,38 +108,29 @@ fn build_slang_wrapper(slang: &Path, wrapper_install: &Path) {
 fn main() -> Result<()> {
     let out_dir = PathBuf::from(env::var("OUT_DIR")?);
     println!("cargo:rerun-if-changed=slang_wrapper");
-
-    let (slang_install, wrapper_install, link_type) = match env::var("SLANG_INSTALL_PATH") {
-        Err(_) => {
-            // Build slang from source
-            let download_dir = out_dir.join("slang-src");
-            let slang_src = download_slang(&download_dir)?;
-            let slang_install = out_dir.join("slang-install");
-            let wrapper_install = out_dir.join("slang-wrapper-install");
-
-            build_slang(&slang_src, &slang_install);
-            build_slang_wrapper(&slang_install, &wrapper_install);
-
-            (
-                slang_install.join("lib"),
-                wrapper_install.join("lib"),
-                "static",
-            )
-        }
-        Ok(slang_install) => {
-            // Directly use external slang
-            let slang_install = Path::new(&slang_install);
-            let wrapper_install = out_dir.join("slang-wrapper-install");
-
-            build_slang_wrapper(slang_install, &wrapper_install);
-
-            (
-                slang_install.join("lib"),
-                wrapper_install.join("lib"),
-                "dylib",
-            )
-        }
+    println!("cargo:rerun-if-env-changed=SLANG_INSTALL_PATH");
+    println!("cargo:rerun-if-env-changed=SLANG_SRC_PATH");
+    println!("cargo:rerun-if-env-changed=PKG_CONFIG_PATH");
+
+    let wrapper_install = out_dir.join("slang-wrapper-install");
+    let (slang_lib, link_type) = if let Ok(slang_install) = env::var("SLANG_INSTALL_PATH") {
+        // Directly use external slang
+        let slang_install = Path::new(&slang_install);
+        build_slang_wrapper(slang_install, &wrapper_install);
+        (slang_install.join("lib"), "dylib")
+    } else if let Some((slang_prefix, slang_lib)) = system_slang() {
+        // Use the slang installed on the system
+        build_slang_wrapper(&slang_prefix, &wrapper_install);
+        (slang_lib, "dylib")
+    } else {
+        // Build slang from source
+        let slang_src = slang_source(&out_dir)?;
+        let slang_install = out_dir.join("slang-install");
+        build_slang(&slang_src, &slang_install);
+        build_slang_wrapper(&slang_install, &wrapper_install);
+        (slang_install.join("lib"), "static")
     };
+    let wrapper_install = wrapper_install.join("lib");
 
     let bindings = bindgen::Builder::default()
         .clang_arg("-x")
@@ -92,7 +140,7 @@ fn main() -> Result<()> {
         .generate()
         .expect("Unable to generate bindings");
 
-    println!("cargo:rustc-link-search=native={}", slang_install.display());
+    println!("cargo:rustc-link-search=native={}", slang_lib.display());
     println!(
         "cargo:rustc-link-search=native={}",
         wrapper_install.display()
@@ -0,0 +1,42 @@
+//------------------------------------------------------------------------------
+//! @file CollectingClient.h
+//! @brief Diagnostic client that collects structured diagnostics
+//------------------------------------------------------------------------------
+#pragma once
+
+#include <span>
+#include <string>
+#include <vector>
+
+#include "slang/diagnostics/DiagnosticClient.h"
+
+namespace slang {
+
+struct CollectedDiagnostic {
+    std::string file;
+    unsigned int startLine = 0;
+    unsigned int startCol = 0;
+    unsigned int endLine = 0;
+    unsigned int endCol = 0;
+    DiagnosticSeverity severity = DiagnosticSeverity::Ignored;
+    std::string code;
+    std::string option;
+    std::string message;
+    std::vector<CollectedDiagnostic> notes;
+    std::vector<CollectedDiagnostic> expansions;
+};
+
+class CollectingClient : public DiagnosticClient {
+public:
+    void report(const ReportedDiagnostic& diagnostic) override;
+
+    const std::vector<CollectedDiagnostic>& getDiagnostics() const { return diagnostics; }
+
+private:
+    std::vector<CollectedDiagnostic> diagnostics;
+
+    CollectedDiagnostic collect(SourceLocation loc, std::span<const SourceRange> ranges,
+                                DiagnosticSeverity severity, std::string_view message) const;
+};
+
+} // namespace slang
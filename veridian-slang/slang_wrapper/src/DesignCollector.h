@@ -0,0 +1,66 @@
+//------------------------------------------------------------------------------
+//! @file DesignCollector.h
+//! @brief Collects symbols and references from an elaborated design
+//------------------------------------------------------------------------------
+#pragma once
+
+#include <string>
+#include <unordered_map>
+#include <vector>
+
+#include "slang/ast/Compilation.h"
+#include "slang/text/SourceManager.h"
+
+namespace slang {
+
+struct CollectedLocation {
+    std::string file;
+    unsigned int startLine = 0;
+    unsigned int startCol = 0;
+    unsigned int endLine = 0;
+    unsigned int endCol = 0;
+};
+
+struct CollectedSymbol {
+    std::string name;
+    std::string kind;
+    // hierarchical path of the symbol in the elaborated design
+    std::string path;
+    // location of the symbol's name where it is declared
+    CollectedLocation location;
+    // resolved type, or the definition name of an instance
+    std::string type;
+    // value of a parameter, empty for other symbols
+    std::string value;
+};
+
+struct CollectedReference {
+    CollectedLocation location;
+    // index of the referenced symbol
+    size_t symbol;
+};
+
+class DesignCollector {
+public:
+    explicit DesignCollector(const SourceManager& sourceManager) : sourceManager(sourceManager) {}
+
+    // visit the whole elaborated design
+    void collect(ast::Compilation& compilation);
+
+    // index of a symbol, adding it if it has not been seen, -1 for symbols without a location
+    std::ptrdiff_t add(const ast::Symbol& symbol);
+    void addReference(const ast::Symbol& symbol, SourceRange range);
+
+    const std::vector<CollectedSymbol>& getSymbols() const { return symbols; }
+    const std::vector<CollectedReference>& getReferences() const { return references; }
+
+private:
+    const SourceManager& sourceManager;
+    std::vector<CollectedSymbol> symbols;
+    std::vector<CollectedReference> references;
+    std::unordered_map<const ast::Symbol*, size_t> indices;
+
+    bool locate(SourceRange range, CollectedLocation& result) const;
+};
+
+} // namespace slang
@@ -0,0 +1,72 @@
+//------------------------------------------------------------------------------
+// collecting_client.cpp
+// Diagnostic client that collects structured diagnostics
+//------------------------------------------------------------------------------
+#include "CollectingClient.h"
+
+#include <fmt/format.h>
+
+#include "slang/diagnostics/DiagnosticEngine.h"
+#include "slang/text/SourceManager.h"
+
+namespace slang {
+
+void CollectingClient::report(const ReportedDiagnostic& diag) {
+    SmallVector<SourceRange, 8> mappedRanges;
+    engine->mapSourceRanges(diag.location, diag.ranges, mappedRanges);
+
+    auto collected = collect(diag.location, mappedRanges, diag.severity, diag.formattedMessage);
+    collected.code = std::string(toString(diag.originalDiagnostic.code));
+    collected.option = std::string(engine->getOptionName(diag.originalDiagnostic.code));
+
+    // macro expansions are reported innermost first
+    for (auto it = diag.expansionLocs.rbegin(); it != diag.expansionLocs.rend(); it++) {
+        SourceLocation loc = *it;
+        std::string name(sourceManager->getMacroName(loc));
+        if (name.empty())
+            name = "expanded from here";
+        else
+            name = fmt::format("expanded from macro '{}'", name);
+
+        SmallVector<SourceRange, 8> macroRanges;
+        engine->mapSourceRanges(loc, diag.ranges, macroRanges);
+        collected.expansions.push_back(collect(sourceManager->getFullyOriginalLoc(loc),
+                                               macroRanges, DiagnosticSeverity::Note, name));
+    }
+
+    // notes are issued after the diagnostic they belong to
+    if (diag.severity == DiagnosticSeverity::Note && !diagnostics.empty())
+        diagnostics.back().notes.push_back(std::move(collected));
+    else
+        diagnostics.push_back(std::move(collected));
+}
+
+CollectedDiagnostic CollectingClient::collect(SourceLocation loc,
+                                              std::span<const SourceRange> ranges,
+                                              DiagnosticSeverity severity,
+                                              std::string_view message) const {
+    CollectedDiagnostic result;
+    result.severity = severity;
+    result.message = std::string(message);
+    if (loc == SourceLocation::NoLocation)
+        return result;
+
+    result.file = std::string(sourceManager->getFileName(loc));
+    result.startLine = (unsigned int)sourceManager->getLineNumber(loc);
+    result.startCol = (unsigned int)sourceManager->getColumnNumber(loc);
+    result.endLine = result.startLine;
+    result.endCol = result.startCol;
+    // extend to the end of the highlighted range that contains the location
+    for (auto& range : ranges) {
+        if (range.start().buffer() != loc.buffer() || range.start() > loc ||
+            range.end() < loc) {
+            continue;
+        }
+        result.endLine = (unsigned int)sourceManager->getLineNumber(range.end());
+        result.endCol = (unsigned int)sourceManager->getColumnNumber(range.end());
+        break;
+    }
+    return result;
+}
+
+} // namespace slang
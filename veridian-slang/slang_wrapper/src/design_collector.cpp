@@ -0,0 +1,114 @@
+//------------------------------------------------------------------------------
+// design_collector.cpp
+// Collects symbols and references from an elaborated design
+//------------------------------------------------------------------------------
+#include "DesignCollector.h"
+
+#include "slang/ast/ASTVisitor.h"
+#include "slang/ast/symbols/InstanceSymbols.h"
+#include "slang/ast/symbols/ParameterSymbols.h"
+#include "slang/syntax/AllSyntax.h"
+
+namespace slang {
+
+using namespace ast;
+
+namespace {
+
+struct Visitor : public ASTVisitor<Visitor, true, true> {
+    DesignCollector& collector;
+
+    explicit Visitor(DesignCollector& collector) : collector(collector) {}
+
+    void handle(const ValueSymbol& symbol) {
+        collector.add(symbol);
+        visitDefault(symbol);
+    }
+
+    void handle(const InstanceSymbol& symbol) {
+        collector.add(symbol);
+        // the definition name in `mod_name #(...) inst (...)` refers to the definition
+        auto syntax = symbol.getSyntax();
+        if (syntax && syntax->parent &&
+            syntax->parent->kind == syntax::SyntaxKind::HierarchyInstantiation) {
+            auto& inst = syntax->parent->as<syntax::HierarchyInstantiationSyntax>();
+            collector.addReference(symbol.getDefinition(), inst.type.range());
+        }
+        visitDefault(symbol);
+    }
+
+    void handle(const NamedValueExpression& expr) {
+        collector.addReference(expr.symbol, expr.sourceRange);
+        visitDefault(expr);
+    }
+
+    void handle(const HierarchicalValueExpression& expr) {
+        collector.addReference(expr.symbol, expr.sourceRange);
+        visitDefault(expr);
+    }
+};
+
+} // namespace
+
+void DesignCollector::collect(Compilation& compilation) {
+    Visitor visitor(*this);
+    compilation.getRoot().visit(visitor);
+}
+
+std::ptrdiff_t DesignCollector::add(const Symbol& symbol) {
+    auto it = indices.find(&symbol);
+    if (it != indices.end())
+        return (std::ptrdiff_t)it->second;
+
+    CollectedSymbol result;
+    SourceRange range(symbol.location, symbol.location + symbol.name.length());
+    if (symbol.name.empty() || !locate(range, result.location))
+        return -1;
+
+    result.name = std::string(symbol.name);
+    result.kind = std::string(toString(symbol.kind));
+    if (symbol.kind != SymbolKind::Definition)
+        result.path = symbol.getHierarchicalPath();
+    if (symbol.isValue())
+        result.type = symbol.as<ValueSymbol>().getType().toString();
+    if (symbol.kind == SymbolKind::Parameter)
+        result.value = symbol.as<ParameterSymbol>().getValue().toString();
+    if (symbol.kind == SymbolKind::Instance)
+        result.type = std::string(symbol.as<InstanceSymbol>().getDefinition().name);
+
+    indices.emplace(&symbol, symbols.size());
+    symbols.push_back(std::move(result));
+    return (std::ptrdiff_t)symbols.size() - 1;
+}
+
+void DesignCollector::addReference(const Symbol& symbol, SourceRange range) {
+    auto index = add(symbol);
+    if (index < 0)
+        return;
+    CollectedReference reference;
+    if (!locate(range, reference.location))
+        return;
+    reference.symbol = (size_t)index;
+    references.push_back(std::move(reference));
+}
+
+bool DesignCollector::locate(SourceRange range, CollectedLocation& result) const {
+    // references written inside macros are reported where the macro is used
+    SourceLocation start = sourceManager.getFullyOriginalLoc(range.start());
+    SourceLocation end = sourceManager.getFullyOriginalLoc(range.end());
+    if (!start.valid() || start.buffer() == SourceLocation::NoLocation.buffer())
+        return false;
+    if (end.buffer() != start.buffer() || end < start)
+        end = start;
+
+    result.file = std::string(sourceManager.getFileName(start));
+    if (result.file.empty())
+        return false;
+    result.startLine = (unsigned int)sourceManager.getLineNumber(start);
+    result.startCol = (unsigned int)sourceManager.getColumnNumber(start);
+    result.endLine = (unsigned int)sourceManager.getLineNumber(end);
+    result.endCol = (unsigned int)sourceManager.getColumnNumber(end);
+    return true;
+}
+
+} // namespace slang
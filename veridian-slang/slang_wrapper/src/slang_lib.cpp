@@ -1,17 +1,27 @@
 #include "BasicClient.h"
+#include "CollectingClient.h"
+#include "DesignCollector.h"
 #include "slang_wrapper.h"
 #include <array>
+#include <cstring>
+#include <fstream>
 #include <iostream>
 #include <filesystem>
+#include <mutex>
+#include <optional>
+#include <unordered_map>
+#include <unordered_set>
 #include <fmt/format.h>
 #include <slang/ast/Compilation.h>
 #include <slang/ast/types/TypePrinter.h>
 #include <slang/diagnostics/DiagnosticClient.h>
 #include <slang/diagnostics/DiagnosticEngine.h>
 #include <slang/diagnostics/Diagnostics.h>
+#include <slang/parsing/Parser.h>
 #include <slang/parsing/Preprocessor.h>
 #include <slang/syntax/SyntaxTree.h>
 #include <slang/text/SourceManager.h>
+#include <slang/util/LanguageVersion.h>
 
 namespace fs = std::filesystem;
 
@@ -126,5 +136,415 @@ char* compile_paths(const char** paths, unsigned int num_paths) {
 }
 
 void delete_report(char* report) {
-    delete report;
+    delete[] report;
+}
+
+// Private functions
+static char* copy_string(const std::string& s) {
+    return strcpy(new char[s.length() + 1], s.c_str());
+}
+
+static slang_diagnostic* to_c_diagnostics(const std::vector<CollectedDiagnostic>& diags);
+
+static void to_c_diagnostic(const CollectedDiagnostic& diag, slang_diagnostic& result) {
+    result.file = copy_string(diag.file);
+    result.start_line = diag.startLine;
+    result.start_col = diag.startCol;
+    result.end_line = diag.endLine;
+    result.end_col = diag.endCol;
+    switch (diag.severity) {
+    case DiagnosticSeverity::Note: result.severity = SLANG_NOTE; break;
+    case DiagnosticSeverity::Warning: result.severity = SLANG_WARNING; break;
+    case DiagnosticSeverity::Error: result.severity = SLANG_ERROR; break;
+    case DiagnosticSeverity::Fatal: result.severity = SLANG_FATAL; break;
+    default: result.severity = SLANG_IGNORED; break;
+    }
+    result.code = copy_string(diag.code);
+    result.option = copy_string(diag.option);
+    result.message = copy_string(diag.message);
+    result.notes = to_c_diagnostics(diag.notes);
+    result.num_notes = (unsigned int)diag.notes.size();
+    result.expansions = to_c_diagnostics(diag.expansions);
+    result.num_expansions = (unsigned int)diag.expansions.size();
+}
+
+static slang_diagnostic* to_c_diagnostics(const std::vector<CollectedDiagnostic>& diags) {
+    if (diags.empty())
+        return nullptr;
+    auto result = new slang_diagnostic[diags.size()];
+    for (size_t i = 0; i < diags.size(); i++) {
+        to_c_diagnostic(diags[i], result[i]);
+    }
+    return result;
+}
+
+static void free_c_diagnostics(slang_diagnostic* diags, unsigned int num) {
+    if (!diags)
+        return;
+    for (unsigned int i = 0; i < num; i++) {
+        delete[] diags[i].file;
+        delete[] diags[i].code;
+        delete[] diags[i].option;
+        delete[] diags[i].message;
+        free_c_diagnostics(diags[i].notes, diags[i].num_notes);
+        free_c_diagnostics(diags[i].expansions, diags[i].num_expansions);
+    }
+    delete[] diags;
+}
+
+// Private function
+static void apply_options(const slang_options* opts, Bag& options,
+                          std::vector<std::string>& warnings,
+                          std::vector<CollectedDiagnostic>& errors) {
+    parsing::PreprocessorOptions ppOptions;
+    parsing::ParserOptions parserOptions;
+    ast::CompilationOptions compilationOptions;
+    for (unsigned int i = 0; i < opts->num_defines; i++) {
+        ppOptions.predefines.emplace_back(opts->defines[i]);
+    }
+    for (unsigned int i = 0; i < opts->num_top_modules; i++) {
+        compilationOptions.topModules.emplace(opts->top_modules[i]);
+    }
+    if (opts->language_version && *opts->language_version) {
+        auto version = parseLanguageVersion(opts->language_version);
+        if (version) {
+            ppOptions.languageVersion = *version;
+            parserOptions.languageVersion = *version;
+            compilationOptions.languageVersion = *version;
+        }
+        else {
+            CollectedDiagnostic error;
+            error.severity = DiagnosticSeverity::Error;
+            error.message = fmt::format("unknown language version '{}'", opts->language_version);
+            errors.push_back(std::move(error));
+        }
+    }
+    for (unsigned int i = 0; i < opts->num_warnings; i++) {
+        std::string_view warning = opts->warnings[i];
+        if (warning.starts_with("-W"))
+            warning.remove_prefix(2);
+        warnings.emplace_back(warning);
+    }
+
+    options.set(ppOptions);
+    options.set(parserOptions);
+    options.set(compilationOptions);
+}
+
+// Private function
+static std::string options_key(const slang_options* opts) {
+    std::string key;
+    if (!opts)
+        return key;
+    auto append = [&](const char** strings, unsigned int num) {
+        for (unsigned int i = 0; i < num; i++) {
+            key.append(strings[i]);
+            key.push_back('\0');
+        }
+        key.push_back('\n');
+    };
+    append(opts->include_dirs, opts->num_include_dirs);
+    append(opts->defines, opts->num_defines);
+    append(opts->warnings, opts->num_warnings);
+    append(opts->top_modules, opts->num_top_modules);
+    if (opts->language_version)
+        key.append(opts->language_version);
+    return key;
+}
+
+// Private function
+static std::optional<std::string> read_file(const std::string& path) {
+    std::ifstream file(path, std::ios::binary);
+    if (!file)
+        return std::nullopt;
+    return std::string(std::istreambuf_iterator<char>(file), std::istreambuf_iterator<char>());
+}
+
+static size_t fingerprint(std::string_view text) {
+    return std::hash<std::string_view>{}(text);
+}
+
+struct CachedTree {
+    size_t fingerprint;
+    std::shared_ptr<SyntaxTree> tree;
+};
+
+// syntax trees are cached per file and only reparsed when their text changes
+struct slang_session {
+    std::mutex mutex;
+    std::unique_ptr<SourceManager> sm;
+    std::string optionsKey;
+    std::vector<CollectedDiagnostic> includeErrors;
+    std::unordered_map<std::string, CachedTree> trees;
+    // included headers and the fingerprint of their text when they were read
+    std::unordered_map<std::string, size_t> headers;
+    // buffers held by the source manager that are no longer used
+    size_t staleBuffers = 0;
+    // the source manager can't replace a buffer, so changed texts are assigned under a new
+    // name, mapped back to the name of the source
+    std::unordered_set<std::string> assigned;
+    std::unordered_map<std::string, std::string> renamed;
+
+    void reset(const slang_options* opts) {
+        sm = std::make_unique<SourceManager>();
+        optionsKey = options_key(opts);
+        includeErrors.clear();
+        trees.clear();
+        headers.clear();
+        staleBuffers = 0;
+        assigned.clear();
+        renamed.clear();
+        if (!opts)
+            return;
+        for (unsigned int i = 0; i < opts->num_include_dirs; i++) {
+            if (auto ec = sm->addUserDirectories(opts->include_dirs[i])) {
+                CollectedDiagnostic error;
+                error.severity = DiagnosticSeverity::Error;
+                error.message = fmt::format("include dir '{}': {}", opts->include_dirs[i],
+                                            ec.message());
+                includeErrors.push_back(std::move(error));
+            }
+        }
+    }
+
+    // headers are cached by the source manager, so any change requires a new one
+    bool headersChanged() const {
+        for (auto& [path, print] : headers) {
+            auto text = read_file(path);
+            if (!text || fingerprint(*text) != print)
+                return true;
+        }
+        return false;
+    }
+
+    // a name the source manager doesn't hold yet for the text of a source
+    std::string bufferName(const std::string& name) {
+        if (assigned.insert(name).second)
+            return name;
+        auto unique = fmt::format("{}#{}", name, renamed.size() + 1);
+        renamed.emplace(unique, name);
+        assigned.insert(unique);
+        return unique;
+    }
+
+    const std::string& sourceName(const std::string& name) const {
+        auto it = renamed.find(name);
+        return it == renamed.end() ? name : it->second;
+    }
+
+    void sourceNames(std::vector<CollectedDiagnostic>& diags) const {
+        for (auto& diag : diags) {
+            diag.file = sourceName(diag.file);
+            sourceNames(diag.notes);
+            sourceNames(diag.expansions);
+        }
+    }
+
+    void recordHeaders() {
+        for (auto buffer : sm->getAllBuffers()) {
+            if (!sm->getIncludedFrom(buffer).valid())
+                continue;
+            std::string path = sm->getFullPath(buffer).string();
+            if (path.empty() || headers.contains(path))
+                continue;
+            if (auto text = read_file(path))
+                headers.emplace(path, fingerprint(*text));
+        }
+    }
+};
+
+slang_session* session_new() {
+    auto session = new slang_session();
+    session->reset(nullptr);
+    return session;
+}
+
+void session_delete(slang_session* session) {
+    delete session;
+}
+
+// Private function
+// bring the session up to date with the sources and options, sources and include dirs that
+// can't be read and invalid options are reported in errors and left out of the compilation
+static void session_update(slang_session* session, const char** names, const char** texts,
+                           unsigned int num_buffers, const char** paths, unsigned int num_paths,
+                           const slang_options* opts, Bag& options,
+                           std::vector<std::string>& warnings,
+                           std::vector<CollectedDiagnostic>& errors) {
+    // replaced buffers are never freed by the source manager, start over once there are many
+    if (session->optionsKey != options_key(opts) || session->headersChanged() ||
+        session->staleBuffers > session->trees.size() + 100) {
+        session->reset(opts);
+    }
+    SourceManager& sm = *session->sm;
+
+    errors = session->includeErrors;
+    if (opts)
+        apply_options(opts, options, warnings, errors);
+
+    // in-memory buffers take the place of the files they are named after
+    std::vector<std::pair<std::string, std::string>> sources;
+    sources.reserve(num_buffers + num_paths);
+    for (unsigned int i = 0; i < num_buffers; i++) {
+        sources.emplace_back(names[i], texts[i]);
+    }
+    for (unsigned int i = 0; i < num_paths; i++) {
+        auto text = read_file(paths[i]);
+        if (!text) {
+            // report unreadable files without a location
+            CollectedDiagnostic error;
+            error.file = paths[i];
+            error.severity = DiagnosticSeverity::Error;
+            error.message = "unable to read file";
+            errors.push_back(std::move(error));
+            continue;
+        }
+        sources.emplace_back(paths[i], std::move(*text));
+    }
+
+    std::unordered_map<std::string, CachedTree> trees;
+    for (auto& [name, text] : sources) {
+        size_t print = fingerprint(text);
+        auto cached = session->trees.find(name);
+        if (cached != session->trees.end()) {
+            if (cached->second.fingerprint == print) {
+                trees.emplace(name, std::move(cached->second));
+                continue;
+            }
+            session->staleBuffers++;
+        }
+        auto buffer = sm.assignText(session->bufferName(name), text);
+        trees.emplace(name, CachedTree{print, SyntaxTree::fromBuffer(buffer, sm, options)});
+    }
+    session->staleBuffers += session->trees.size() - std::min(session->trees.size(), trees.size());
+    session->trees = std::move(trees);
+    session->recordHeaders();
+}
+
+// Private function
+static std::unique_ptr<ast::Compilation> session_compile(slang_session* session,
+                                                         const Bag& options) {
+    auto compilation = std::make_unique<ast::Compilation>(options);
+    for (auto& [name, cached] : session->trees) {
+        compilation->addSyntaxTree(cached.tree);
+    }
+    return compilation;
+}
+
+slang_diagnostics* session_diagnose(slang_session* session, const char** names,
+                                    const char** texts, unsigned int num_buffers,
+                                    const char** paths, unsigned int num_paths,
+                                    const slang_options* opts) {
+    std::lock_guard<std::mutex> lock(session->mutex);
+    auto result = new slang_diagnostics{nullptr, 0};
+
+    Bag options;
+    std::vector<std::string> warnings;
+    std::vector<CollectedDiagnostic> errors;
+    session_update(session, names, texts, num_buffers, paths, num_paths, opts, options, warnings,
+                   errors);
+    auto compilation = session_compile(session, options);
+
+    DiagnosticEngine diagEngine(*session->sm);
+    Diagnostics optionDiags = diagEngine.setWarningOptions(warnings);
+    Diagnostics pragmaDiags = diagEngine.setMappingsFromPragmas();
+
+    auto client = std::make_shared<CollectingClient>();
+    diagEngine.addClient(client);
+
+    for (auto& diag : optionDiags) diagEngine.issue(diag);
+    for (auto& diag : compilation->getAllDiagnostics()) diagEngine.issue(diag);
+
+    // errors without a location come first
+    auto diags = std::move(errors);
+    diags.insert(diags.end(), client->getDiagnostics().begin(), client->getDiagnostics().end());
+    session->sourceNames(diags);
+    result->diagnostics = to_c_diagnostics(diags);
+    result->num_diagnostics = (unsigned int)diags.size();
+    return result;
+}
+
+// Private function
+static void to_c_location(const slang_session* session, const CollectedLocation& location,
+                          slang_location& result) {
+    result.file = copy_string(session->sourceName(location.file));
+    result.start_line = location.startLine;
+    result.start_col = location.startCol;
+    result.end_line = location.endLine;
+    result.end_col = location.endCol;
+}
+
+slang_design* session_design(slang_session* session, const char** names, const char** texts,
+                             unsigned int num_buffers, const char** paths,
+                             unsigned int num_paths, const slang_options* opts) {
+    std::lock_guard<std::mutex> lock(session->mutex);
+    auto result = new slang_design{nullptr, 0, nullptr, 0};
+
+    Bag options;
+    std::vector<std::string> warnings;
+    std::vector<CollectedDiagnostic> errors;
+    session_update(session, names, texts, num_buffers, paths, num_paths, opts, options, warnings,
+                   errors);
+    auto compilation = session_compile(session, options);
+
+    DesignCollector collector(*session->sm);
+    collector.collect(*compilation);
+
+    auto& symbols = collector.getSymbols();
+    if (!symbols.empty()) {
+        result->symbols = new slang_symbol[symbols.size()];
+        result->num_symbols = (unsigned int)symbols.size();
+    }
+    for (size_t i = 0; i < symbols.size(); i++) {
+        auto& symbol = result->symbols[i];
+        symbol.name = copy_string(symbols[i].name);
+        symbol.kind = copy_string(symbols[i].kind);
+        symbol.path = copy_string(symbols[i].path);
+        to_c_location(session, symbols[i].location, symbol.location);
+        symbol.type = copy_string(symbols[i].type);
+        symbol.value = copy_string(symbols[i].value);
+    }
+    auto& references = collector.getReferences();
+    if (!references.empty()) {
+        result->references = new slang_reference[references.size()];
+        result->num_references = (unsigned int)references.size();
+    }
+    for (size_t i = 0; i < references.size(); i++) {
+        to_c_location(session, references[i].location, result->references[i].location);
+        result->references[i].symbol = (unsigned int)references[i].symbol;
+    }
+    return result;
+}
+
+void delete_design(slang_design* design) {
+    if (!design)
+        return;
+    for (unsigned int i = 0; i < design->num_symbols; i++) {
+        auto& symbol = design->symbols[i];
+        delete[] symbol.name;
+        delete[] symbol.kind;
+        delete[] symbol.path;
+        delete[] symbol.location.file;
+        delete[] symbol.type;
+        delete[] symbol.value;
+    }
+    delete[] design->symbols;
+    for (unsigned int i = 0; i < design->num_references; i++) {
+        delete[] design->references[i].location.file;
+    }
+    delete[] design->references;
+    delete design;
+}
+
+slang_diagnostics* diagnose_sources(const char** names, const char** texts,
+                                    unsigned int num_buffers, const char** paths,
+                                    unsigned int num_paths, const slang_options* opts) {
+    slang_session session;
+    session.reset(opts);
+    return session_diagnose(&session, names, texts, num_buffers, paths, num_paths, opts);
+}
+
+void delete_diagnostics(slang_diagnostics* diagnostics) {
+    free_c_diagnostics(diagnostics->diagnostics, diagnostics->num_diagnostics);
+    delete diagnostics;
 }
@@ -1,10 +1,124 @@
 #pragma once
 
 extern "C" {
+// severity of a structured diagnostic
+enum slang_severity {
+    SLANG_IGNORED = 0,
+    SLANG_NOTE = 1,
+    SLANG_WARNING = 2,
+    SLANG_ERROR = 3,
+    SLANG_FATAL = 4,
+};
+
+// a diagnostic with its location, lines and columns are 1-based and 0 if there is no location
+struct slang_diagnostic {
+    const char* file;
+    unsigned int start_line;
+    unsigned int start_col;
+    unsigned int end_line;
+    unsigned int end_col;
+    enum slang_severity severity;
+    // name of the diagnostic, ex. "IndexOOB"
+    const char* code;
+    // name of the warning option, ex. "index-oob", empty if there is none
+    const char* option;
+    const char* message;
+    // notes attached to the diagnostic
+    struct slang_diagnostic* notes;
+    unsigned int num_notes;
+    // macro expansion backtrace, innermost expansion first
+    struct slang_diagnostic* expansions;
+    unsigned int num_expansions;
+};
+
+// compilation options, every list may be empty
+struct slang_options {
+    const char** include_dirs;
+    unsigned int num_include_dirs;
+    // macros as NAME or NAME=VALUE
+    const char** defines;
+    unsigned int num_defines;
+    // warning options as passed to -W, ex. "everything" or "no-width-trunc"
+    const char** warnings;
+    unsigned int num_warnings;
+    const char** top_modules;
+    unsigned int num_top_modules;
+    // ex. "1800-2017" or "1800-2023", null or empty for the default
+    const char* language_version;
+};
+
+struct slang_diagnostics {
+    struct slang_diagnostic* diagnostics;
+    unsigned int num_diagnostics;
+};
+
+// a source range, lines and columns are 1-based
+struct slang_location {
+    const char* file;
+    unsigned int start_line;
+    unsigned int start_col;
+    unsigned int end_line;
+    unsigned int end_col;
+};
+
+// a symbol of the elaborated design, a declaration elaborated in several instances is reported
+// once per instance
+struct slang_symbol {
+    const char* name;
+    // slang symbol kind, ex. "Variable", "Parameter" or "Instance"
+    const char* kind;
+    // hierarchical path, ex. "top.u_fifo.WIDTH", empty for definitions
+    const char* path;
+    // the symbol's name where it is declared
+    struct slang_location location;
+    // resolved type, or the definition name of an instance
+    const char* type;
+    // value of a parameter, empty for other symbols
+    const char* value;
+};
+
+// a use of a symbol
+struct slang_reference {
+    struct slang_location location;
+    // index into slang_design.symbols
+    unsigned int symbol;
+};
+
+struct slang_design {
+    struct slang_symbol* symbols;
+    unsigned int num_symbols;
+    struct slang_reference* references;
+    unsigned int num_references;
+};
+
+// a long-lived compilation that caches syntax trees between calls, safe to share between threads
+struct slang_session;
+
 void delete_report(char* report);
 char* compile_source(const char* name, const char* text);
 char* compile_path(const char* path);
 char* compile_sources(const char** names, const char** texts,
                       unsigned int num_files);
 char* compile_paths(const char** paths, unsigned int num_paths);
+
+// compile in-memory buffers, given as names and texts, together with files on disk
+struct slang_diagnostics* diagnose_sources(const char** names, const char** texts,
+                                           unsigned int num_buffers, const char** paths,
+                                           unsigned int num_paths,
+                                           const struct slang_options* options);
+void delete_diagnostics(struct slang_diagnostics* diagnostics);
+
+struct slang_session* session_new();
+void session_delete(struct slang_session* session);
+// like diagnose_sources, only files whose text changed since the last call are reparsed
+struct slang_diagnostics* session_diagnose(struct slang_session* session, const char** names,
+                                           const char** texts, unsigned int num_buffers,
+                                           const char** paths, unsigned int num_paths,
+                                           const struct slang_options* options);
+// elaborate the sources and return the symbols and references of the design
+struct slang_design* session_design(struct slang_session* session, const char** names,
+                                    const char** texts, unsigned int num_buffers,
+                                    const char** paths, unsigned int num_paths,
+                                    const struct slang_options* options);
+void delete_design(struct slang_design* design);
 }
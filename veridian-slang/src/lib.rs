@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 use std::error;
-use std::ffi::{CStr, CString};
+use std::ffi::{c_char, CStr, CString};
 use std::path::PathBuf;
 
 mod wrapper;
@@ -34,16 +34,376 @@ pub fn slang_compile(paths: Vec<PathBuf>) -> Result<String, Box<dyn error::Error
     Ok(result)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlangSeverity {
+    Ignored,
+    Note,
+    Warning,
+    Error,
+    Fatal,
+}
+
+/// a diagnostic reported by slang, lines and columns are 1-based
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlangDiagnostic {
+    pub file: PathBuf,
+    // start and end (exclusive) line and column, None if slang gave no location
+    pub range: Option<((u32, u32), (u32, u32))>,
+    pub severity: SlangSeverity,
+    // name of the diagnostic, ex. "IndexOOB"
+    pub code: String,
+    // name of the warning option, ex. "index-oob", empty if there is none
+    pub option: String,
+    pub message: String,
+    pub notes: Vec<SlangDiagnostic>,
+    // macro expansion backtrace, innermost expansion first
+    pub expansions: Vec<SlangDiagnostic>,
+}
+
+fn c_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn convert_diagnostics(ptr: *const slang_diagnostic, num: u32) -> Vec<SlangDiagnostic> {
+    if ptr.is_null() || num == 0 {
+        return Vec::new();
+    }
+    let diags = unsafe { std::slice::from_raw_parts(ptr, num as usize) };
+    diags
+        .iter()
+        .map(|diag| SlangDiagnostic {
+            file: PathBuf::from(c_string(diag.file)),
+            range: if diag.start_line == 0 {
+                None
+            } else {
+                Some((
+                    (diag.start_line, diag.start_col),
+                    (diag.end_line, diag.end_col),
+                ))
+            },
+            severity: match diag.severity {
+                slang_severity_SLANG_NOTE => SlangSeverity::Note,
+                slang_severity_SLANG_WARNING => SlangSeverity::Warning,
+                slang_severity_SLANG_ERROR => SlangSeverity::Error,
+                slang_severity_SLANG_FATAL => SlangSeverity::Fatal,
+                _ => SlangSeverity::Ignored,
+            },
+            code: c_string(diag.code),
+            option: c_string(diag.option),
+            message: c_string(diag.message),
+            notes: convert_diagnostics(diag.notes, diag.num_notes),
+            expansions: convert_diagnostics(diag.expansions, diag.num_expansions),
+        })
+        .collect()
+}
+
+/// options for a slang compilation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SlangOptions {
+    pub include_dirs: Vec<PathBuf>,
+    // macros as NAME or NAME=VALUE
+    pub defines: Vec<String>,
+    // warning options, ex. "-Wno-width-trunc" or "everything"
+    pub warnings: Vec<String>,
+    pub top_modules: Vec<String>,
+    // ex. "1800-2017", empty for the default
+    pub language_version: String,
+}
+
+fn c_strings<T: AsRef<str>>(strings: &[T]) -> Result<Vec<CString>, Box<dyn error::Error>> {
+    let mut result: Vec<CString> = Vec::new();
+    for string in strings {
+        result.push(CString::new(string.as_ref())?);
+    }
+    Ok(result)
+}
+
+fn c_paths(paths: &[PathBuf]) -> Result<Vec<CString>, Box<dyn error::Error>> {
+    let mut result: Vec<CString> = Vec::new();
+    for path in paths {
+        result.push(CString::new(
+            path.to_str().ok_or("path is not valid unicode")?,
+        )?);
+    }
+    Ok(result)
+}
+
+fn c_ptrs(strings: &[CString]) -> Vec<*const c_char> {
+    strings.iter().map(|x| x.as_ptr()).collect()
+}
+
+/// the text of a file, used instead of the file on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlangBuffer {
+    pub name: PathBuf,
+    pub text: String,
+}
+
+/// a source location, lines and columns are 1-based
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SlangLocation {
+    pub file: PathBuf,
+    // start and end (exclusive) line and column
+    pub range: ((u32, u32), (u32, u32)),
+}
+
+/// a symbol of the elaborated design, a declaration elaborated in several instances is reported
+/// once per instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlangSymbol {
+    pub name: String,
+    // slang symbol kind, ex. "Variable", "Parameter" or "Instance"
+    pub kind: String,
+    // hierarchical path, ex. "top.u_fifo.WIDTH", empty for definitions
+    pub path: String,
+    // the symbol's name where it is declared
+    pub location: SlangLocation,
+    // resolved type, or the definition name of an instance
+    pub type_name: String,
+    // value of a parameter, empty for other symbols
+    pub value: String,
+}
+
+/// a use of a symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlangReference {
+    pub location: SlangLocation,
+    // index into SlangDesign::symbols
+    pub symbol: usize,
+}
+
+/// the symbols and references of an elaborated design
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SlangDesign {
+    pub symbols: Vec<SlangSymbol>,
+    pub references: Vec<SlangReference>,
+}
+
+fn convert_location(location: &slang_location) -> SlangLocation {
+    SlangLocation {
+        file: PathBuf::from(c_string(location.file)),
+        range: (
+            (location.start_line, location.start_col),
+            (location.end_line, location.end_col),
+        ),
+    }
+}
+
+unsafe fn convert_design(design: &slang_design) -> SlangDesign {
+    let symbols = if design.symbols.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(design.symbols, design.num_symbols as usize)
+    };
+    let references = if design.references.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(design.references, design.num_references as usize)
+    };
+    SlangDesign {
+        symbols: symbols
+            .iter()
+            .map(|x| SlangSymbol {
+                name: c_string(x.name),
+                kind: c_string(x.kind),
+                path: c_string(x.path),
+                location: convert_location(&x.location),
+                type_name: c_string(x.type_),
+                value: c_string(x.value),
+            })
+            .collect(),
+        references: references
+            .iter()
+            .map(|x| SlangReference {
+                location: convert_location(&x.location),
+                symbol: x.symbol as usize,
+            })
+            .collect(),
+    }
+}
+
+/// a slang compilation kept between calls, files are only reparsed when their text changes
+pub struct SlangSession {
+    session: *mut slang_session,
+}
+
+// the wrapper serializes calls on a session
+unsafe impl Send for SlangSession {}
+unsafe impl Sync for SlangSession {}
+
+/// the arguments of a session call, the CStrings must outlive the call
+struct SessionInputs {
+    _strings: Vec<Vec<CString>>,
+    _language_version: CString,
+    names: Vec<*const c_char>,
+    texts: Vec<*const c_char>,
+    paths: Vec<*const c_char>,
+    _include_dirs: Vec<*const c_char>,
+    _defines: Vec<*const c_char>,
+    _warnings: Vec<*const c_char>,
+    _top_modules: Vec<*const c_char>,
+    options: slang_options,
+}
+
+impl SessionInputs {
+    fn new(
+        buffers: &[SlangBuffer],
+        paths: &[PathBuf],
+        options: &SlangOptions,
+    ) -> Result<Self, Box<dyn error::Error>> {
+        let names: Vec<PathBuf> = buffers.iter().map(|x| x.name.clone()).collect();
+        let texts: Vec<&str> = buffers.iter().map(|x| x.text.as_str()).collect();
+        let strings = vec![
+            c_paths(&names)?,
+            c_strings(&texts)?,
+            c_paths(paths)?,
+            c_paths(&options.include_dirs)?,
+            c_strings(&options.defines)?,
+            c_strings(&options.warnings)?,
+            c_strings(&options.top_modules)?,
+        ];
+        let language_version = CString::new(options.language_version.as_str())?;
+        // the pointers stay valid when the vectors are moved into the struct
+        let mut include_dirs = c_ptrs(&strings[3]);
+        let mut defines = c_ptrs(&strings[4]);
+        let mut warnings = c_ptrs(&strings[5]);
+        let mut top_modules = c_ptrs(&strings[6]);
+        let options = slang_options {
+            include_dirs: include_dirs.as_mut_ptr(),
+            num_include_dirs: include_dirs.len() as u32,
+            defines: defines.as_mut_ptr(),
+            num_defines: defines.len() as u32,
+            warnings: warnings.as_mut_ptr(),
+            num_warnings: warnings.len() as u32,
+            top_modules: top_modules.as_mut_ptr(),
+            num_top_modules: top_modules.len() as u32,
+            language_version: language_version.as_ptr(),
+        };
+        Ok(SessionInputs {
+            names: c_ptrs(&strings[0]),
+            texts: c_ptrs(&strings[1]),
+            paths: c_ptrs(&strings[2]),
+            _include_dirs: include_dirs,
+            _defines: defines,
+            _warnings: warnings,
+            _top_modules: top_modules,
+            options,
+            _strings: strings,
+            _language_version: language_version,
+        })
+    }
+}
+
+impl SlangSession {
+    pub fn new() -> Self {
+        SlangSession {
+            session: unsafe { session_new() },
+        }
+    }
+
+    /// compile in-memory buffers and files with slang and return its diagnostics
+    pub fn diagnostics(
+        &self,
+        buffers: Vec<SlangBuffer>,
+        paths: Vec<PathBuf>,
+        options: &SlangOptions,
+    ) -> Result<Vec<SlangDiagnostic>, Box<dyn error::Error>> {
+        if buffers.is_empty() && paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut inputs = SessionInputs::new(&buffers, &paths, options)?;
+        let report = unsafe {
+            session_diagnose(
+                self.session,
+                inputs.names.as_mut_ptr(),
+                inputs.texts.as_mut_ptr(),
+                inputs.names.len() as u32,
+                inputs.paths.as_mut_ptr(),
+                inputs.paths.len() as u32,
+                &inputs.options,
+            )
+        };
+        if report.is_null() {
+            return Err("slang returned no diagnostics".into());
+        }
+        let result =
+            unsafe { convert_diagnostics((*report).diagnostics, (*report).num_diagnostics) };
+        unsafe {
+            delete_diagnostics(report);
+        }
+        Ok(result)
+    }
+
+    /// elaborate in-memory buffers and files with slang and return the symbols and references
+    /// of the design, files that can't be read are left out
+    pub fn design(
+        &self,
+        buffers: Vec<SlangBuffer>,
+        paths: Vec<PathBuf>,
+        options: &SlangOptions,
+    ) -> Result<SlangDesign, Box<dyn error::Error>> {
+        if buffers.is_empty() && paths.is_empty() {
+            return Ok(SlangDesign::default());
+        }
+        let mut inputs = SessionInputs::new(&buffers, &paths, options)?;
+        let design = unsafe {
+            session_design(
+                self.session,
+                inputs.names.as_mut_ptr(),
+                inputs.texts.as_mut_ptr(),
+                inputs.names.len() as u32,
+                inputs.paths.as_mut_ptr(),
+                inputs.paths.len() as u32,
+                &inputs.options,
+            )
+        };
+        if design.is_null() {
+            return Err("slang returned no design".into());
+        }
+        let result = unsafe { convert_design(&*design) };
+        unsafe {
+            delete_design(design);
+        }
+        Ok(result)
+    }
+}
+
+impl Default for SlangSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SlangSession {
+    fn drop(&mut self) {
+        unsafe {
+            session_delete(self.session);
+        }
+    }
+}
+
+/// compile in-memory buffers and files with slang and return its diagnostics
+pub fn slang_diagnostics(
+    buffers: Vec<SlangBuffer>,
+    paths: Vec<PathBuf>,
+    options: &SlangOptions,
+) -> Result<Vec<SlangDiagnostic>, Box<dyn error::Error>> {
+    SlangSession::new().diagnostics(buffers, paths, options)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serial_test::serial;
     use std::fs::File;
     use std::io::Write;
     use tempdir::TempDir;
 
     #[test]
-    #[serial]
     fn test_paths_wrapper() {
         let dir = TempDir::new("slang_wrapper_tests").unwrap();
         let file_path_1 = dir.path().join("test1.sv");
@@ -84,4 +444,211 @@ mod tests {
         result = result.trim_start().to_owned();
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_slang_diagnostics() {
+        let dir = TempDir::new("slang_wrapper_tests").unwrap();
+        let file_path = dir.path().join("test.sv");
+        let mut f = File::create(&file_path).unwrap();
+        f.write_all(b"module test; logic [1:0] abc; assign abc[2] = 1'b1; endmodule")
+            .unwrap();
+        f.sync_all().unwrap();
+
+        let diags = slang_diagnostics(
+            Vec::new(),
+            vec![file_path.clone()],
+            &SlangOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].file, file_path);
+        assert_eq!(diags[0].range.map(|x| x.0), Some((1, 43)));
+        assert_eq!(diags[0].severity, SlangSeverity::Warning);
+        assert_eq!(diags[0].option, "index-oob");
+        assert_eq!(
+            diags[0].message,
+            "cannot refer to element 2 of \'logic[1:0]\'"
+        );
+    }
+
+    #[test]
+    fn test_slang_options() {
+        let dir = TempDir::new("slang_wrapper_tests").unwrap();
+        let inc_dir = dir.path().join("inc");
+        std::fs::create_dir(&inc_dir).unwrap();
+        let mut f = File::create(inc_dir.join("width.svh")).unwrap();
+        f.write_all(b"`define WIDTH 2").unwrap();
+        f.sync_all().unwrap();
+        let file_path = dir.path().join("test.sv");
+        let mut f = File::create(&file_path).unwrap();
+        f.write_all(
+            b"`include \"width.svh\"\nmodule test; logic [`WIDTH-1:0] abc; assign abc[`INDEX] = 1'b1; endmodule",
+        )
+        .unwrap();
+        f.sync_all().unwrap();
+
+        let mut options = SlangOptions {
+            include_dirs: vec![inc_dir],
+            defines: vec!["INDEX=2".to_owned()],
+            ..Default::default()
+        };
+        let diags = slang_diagnostics(Vec::new(), vec![file_path.clone()], &options).unwrap();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].option, "index-oob");
+
+        options.warnings = vec!["-Wno-index-oob".to_owned()];
+        let diags = slang_diagnostics(Vec::new(), vec![file_path], &options).unwrap();
+        assert!(diags.is_empty());
+    }
+
+    #[test]
+    fn test_slang_buffers() {
+        let dir = TempDir::new("slang_wrapper_tests").unwrap();
+        let file_path_1 = dir.path().join("test1.sv");
+        let mut f = File::create(&file_path_1).unwrap();
+        f.write_all(b"module test1; logic [1:0] abc; assign abc[2] = 1'b1; endmodule")
+            .unwrap();
+        f.sync_all().unwrap();
+        let file_path_2 = dir.path().join("test2.sv");
+
+        // the buffer replaces the saved text of test1.sv, test2.sv only exists in memory
+        let buffers = vec![
+            SlangBuffer {
+                name: file_path_1.clone(),
+                text: "module test1; logic [1:0] abc; assign abc[1] = 1'b1; endmodule".to_owned(),
+            },
+            SlangBuffer {
+                name: file_path_2.clone(),
+                text: "module test2; logic [1:0] abc; assign abc[3] = 1'b1; endmodule".to_owned(),
+            },
+        ];
+        let diags = slang_diagnostics(buffers, Vec::new(), &SlangOptions::default()).unwrap();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].file, file_path_2);
+    }
+
+    #[test]
+    fn test_slang_session() {
+        let dir = TempDir::new("slang_wrapper_tests").unwrap();
+        let file_path = dir.path().join("test.sv");
+        let session = SlangSession::new();
+        let buffer = |index: u32| SlangBuffer {
+            name: file_path.clone(),
+            text: format!(
+                "module test; logic [1:0] abc; assign abc[{}] = 1'b1; endmodule",
+                index
+            ),
+        };
+        let options = SlangOptions::default();
+        let diags = session
+            .diagnostics(vec![buffer(2)], Vec::new(), &options)
+            .unwrap();
+        assert_eq!(diags.len(), 1);
+        // an unchanged buffer reuses the parsed tree
+        let diags = session
+            .diagnostics(vec![buffer(2)], Vec::new(), &options)
+            .unwrap();
+        assert_eq!(diags.len(), 1);
+        let diags = session
+            .diagnostics(vec![buffer(1)], Vec::new(), &options)
+            .unwrap();
+        assert!(diags.is_empty());
+        // a changed text is assigned under a new name, but reported under the name of the source
+        let diags = session
+            .diagnostics(vec![buffer(3)], Vec::new(), &options)
+            .unwrap();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].file, file_path);
+    }
+
+    #[test]
+    fn test_slang_option_errors() {
+        let buffer = SlangBuffer {
+            name: PathBuf::from("/errors.sv"),
+            text: "module errors; logic [1:0] abc; assign abc[2] = 1'b1; endmodule".to_owned(),
+        };
+        let options = SlangOptions {
+            include_dirs: vec![PathBuf::from("/missing/include/dir")],
+            ..Default::default()
+        };
+        // the bad include dir is reported without a location and the sources are still compiled
+        let diags = slang_diagnostics(vec![buffer], Vec::new(), &options).unwrap();
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].file, PathBuf::new());
+        assert_eq!(diags[0].range, None);
+        assert!(diags[0].message.contains("/missing/include/dir"));
+        assert_eq!(diags[1].option, "index-oob");
+    }
+
+    #[test]
+    fn test_slang_threads() {
+        let session = std::sync::Arc::new(SlangSession::new());
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let session = session.clone();
+                std::thread::spawn(move || {
+                    let buffer = SlangBuffer {
+                        name: PathBuf::from(format!("/thread{}.sv", i)),
+                        text: format!(
+                            "module thread{}; logic [1:0] abc; assign abc[2] = 1'b1; endmodule",
+                            i
+                        ),
+                    };
+                    let session_diags = session
+                        .diagnostics(vec![buffer.clone()], Vec::new(), &SlangOptions::default())
+                        .unwrap();
+                    let diags =
+                        slang_diagnostics(vec![buffer], Vec::new(), &SlangOptions::default())
+                            .unwrap();
+                    assert_eq!(session_diags.len(), 1);
+                    assert_eq!(diags.len(), 1);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_slang_design() {
+        let buffer = SlangBuffer {
+            name: PathBuf::from("/design.sv"),
+            text: r#"module child #(parameter int WIDTH = 1) (input logic [WIDTH-1:0] a);
+endmodule
+module top;
+  logic [3:0] data;
+  child #(.WIDTH(4)) u_child (.a(data));
+endmodule
+"#
+            .to_owned(),
+        };
+        let design = SlangSession::new()
+            .design(vec![buffer], Vec::new(), &SlangOptions::default())
+            .unwrap();
+        let width = design
+            .symbols
+            .iter()
+            .find(|x| x.path == "top.u_child.WIDTH")
+            .unwrap();
+        assert_eq!(width.kind, "Parameter");
+        assert_eq!(width.value, "4");
+        assert_eq!(width.location.range, ((1, 29), (1, 34)));
+        let instance = design
+            .symbols
+            .iter()
+            .find(|x| x.path == "top.u_child")
+            .unwrap();
+        assert_eq!(instance.type_name, "child");
+        // `data` is declared on line 4 and used in the port connection on line 5
+        let data = design
+            .symbols
+            .iter()
+            .position(|x| x.path == "top.data")
+            .unwrap();
+        assert!(design
+            .references
+            .iter()
+            .any(|x| x.symbol == data && x.location.range.0 .0 == 5));
+    }
 }
@@ -1,3 +1,4 @@
+#![allow(non_camel_case_types, non_upper_case_globals)]
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
 #[cfg(test)]